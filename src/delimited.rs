@@ -0,0 +1,175 @@
+use crate::error::DlError;
+use crate::parser::data_token::Data;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    Comma,
+    Tab,
+}
+
+// auto reads a number when the cell is one and a string otherwise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Auto,
+    Number,
+    String,
+}
+
+impl ColumnType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(Self::Auto),
+            "number" => Some(Self::Number),
+            "string" => Some(Self::String),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportOptions {
+    pub delimiter: Delimiter,
+    // the first row names the columns and isn't imported
+    pub header: bool,
+    // one type per column, every column is auto when there are none
+    pub column_types: Option<Vec<ColumnType>>,
+}
+
+impl ImportOptions {
+    pub fn new(delimiter: Delimiter) -> Self {
+        Self {
+            delimiter,
+            header: false,
+            column_types: None,
+        }
+    }
+}
+
+// the rows of a csv or tsv file as data, rows are numbered by the line they start on
+pub fn read_rows(contents: &str, options: &ImportOptions) -> Result<Vec<Vec<Data>>, DlError> {
+    let mut records = match options.delimiter {
+        Delimiter::Comma => read_csv_records(contents)?,
+        Delimiter::Tab => read_tsv_records(contents),
+    };
+    if options.header && !records.is_empty() {
+        records.remove(0);
+    }
+
+    let width = match (&options.column_types, records.first()) {
+        (Some(column_types), _) => column_types.len(),
+        (None, Some((_, first))) => first.len(),
+        (None, None) => return Ok(vec![]),
+    };
+
+    let mut ret = vec![];
+    for (row, record) in records {
+        if record.len() != width {
+            return Err(DlError::Import {
+                row,
+                message: format!("specting {width} columns, found {}", record.len()),
+            });
+        }
+
+        let mut datas = vec![];
+        for (i, cell) in record.into_iter().enumerate() {
+            let column_type = match &options.column_types {
+                Some(column_types) => column_types[i],
+                None => ColumnType::Auto,
+            };
+            // NaN and the infinities parse as floats but can't be written back as numbers
+            let number = cell.trim().parse::<f64>().ok().filter(|n| n.is_finite());
+            datas.push(match (column_type, number) {
+                (ColumnType::Auto | ColumnType::Number, Some(n)) => Data::Number(n),
+                (ColumnType::Number, None) => {
+                    return Err(DlError::Import {
+                        row,
+                        message: format!("column {} should be a number, found {cell:?}", i + 1),
+                    })
+                }
+                (ColumnType::Auto | ColumnType::String, _) => Data::String(cell),
+            });
+        }
+        ret.push(datas);
+    }
+    Ok(ret)
+}
+
+// quoted fields can hold commas, line breaks and doubled quotes
+fn read_csv_records(contents: &str) -> Result<Vec<(usize, Vec<String>)>, DlError> {
+    let mut records = vec![];
+    let mut record = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut record_line = 1;
+
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => record.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => (),
+            ('\n', false) => {
+                record.push(std::mem::take(&mut field));
+                records.push((record_line, std::mem::take(&mut record)));
+                line += 1;
+                record_line = line;
+            }
+            ('\n', true) => {
+                field.push(c);
+                line += 1;
+            }
+            (c, _) => field.push(c),
+        }
+    }
+
+    if quoted {
+        return Err(DlError::Import {
+            row: record_line,
+            message: "a quoted field never ends".into(),
+        });
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push((record_line, record));
+    }
+    Ok(records
+        .into_iter()
+        .filter(|(_, record)| record != &vec![String::new()])
+        .collect())
+}
+
+// fields can't hold tabs or line breaks, they are written as \t and \n
+fn read_tsv_records(contents: &str) -> Vec<(usize, Vec<String>)> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| (i + 1, line.split('\t').map(unescape_tsv_field).collect()))
+        .collect()
+}
+
+fn unescape_tsv_field(field: &str) -> String {
+    let mut ret = String::new();
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some(escaped @ ('t' | 'n' | 'r' | '\\'))) => {
+                chars.next();
+                ret.push(match escaped {
+                    't' => '\t',
+                    'n' => '\n',
+                    'r' => '\r',
+                    _ => '\\',
+                });
+            }
+            (c, _) => ret.push(c),
+        }
+    }
+    ret
+}
@@ -1,3 +1,5 @@
+pub mod answer_table;
+pub mod join_plan;
 pub mod operations;
 pub mod recursion_tally;
 pub mod relation;
@@ -7,20 +9,45 @@ pub mod var_context_universe;
 
 use print_macros::*;
 
+use crate::delimited::{read_rows, ColumnType, Delimiter, ImportOptions};
+use crate::error::{DlError, Span};
+use crate::render::{render_outcome, render_outcomes, OutputFormat};
+use crate::storage::Storage;
 use crate::{
     lexer,
     parser::{
-        self, assumption_token::Assumption, defered_relation_token::DeferedRelation,
-        inmediate_relation_token::InmediateRelation, line_token::Line, HasRelId,
+        self,
+        assumption_token::Assumption,
+        conditional_token::Conditional,
+        constraint_token::Constraint,
+        data_token::{Data, DataType},
+        defered_relation_token::DeferedRelation,
+        directive_token::{Declaration, Directive, KeyMode},
+        expresion_token::Expresion,
+        inmediate_relation_token::InmediateRelation,
+        line_token::Line,
+        retraction_token::Retraction,
+        statement_token::Statement,
+        transaction_token::TransactionControl,
+        HasRelId,
     },
 };
-use std::{collections::BTreeMap, fmt, vec};
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt,
+    fs::{read_to_string, write},
+    path::PathBuf,
+    vec,
+};
 
 use self::{
+    answer_table::{AnswerTable, CallPattern, Interpretation},
     recursion_tally::RecursionTally,
     relation::{truth::Truth, Relation},
     truth_list::TruthList,
     var_context::VarContext,
+    var_context_universe::VarContextUniverse,
 };
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, PartialOrd, Ord)]
@@ -29,11 +56,75 @@ pub struct RelId {
     pub column_count: usize,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for RelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.identifier, self.column_count)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum RuntimeError {
-    UnmatchingLine(Line),
     Explanation(String),
     NoContextWhenNeeded,
+    // the relation was never defined, with the known relations of the same name
+    UnknownRelation(RelId, Vec<RelId>),
+    // the values of the constraint's variables for every way it holds
+    ConstraintViolated {
+        constraint: String,
+        bindings: Vec<Vec<(String, Data)>>,
+    },
+    // a generative rule still had new truths to deduce when the recursion limit stopped it
+    RecursionLimit(RelId, usize),
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RuntimeError::Explanation(explanation) => write!(f, "{explanation}"),
+            RuntimeError::NoContextWhenNeeded => write!(f, "there was no context when needed"),
+            RuntimeError::UnknownRelation(rel_id, similar) if similar.is_empty() => {
+                write!(f, "the relation {rel_id} doesn't exist")
+            }
+            RuntimeError::UnknownRelation(rel_id, similar) => write!(
+                f,
+                "the relation {rel_id} doesn't exist, did you mean {}?",
+                similar
+                    .iter()
+                    .map(|rel_id| rel_id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" or ")
+            ),
+            RuntimeError::RecursionLimit(rel_id, limit) => write!(
+                f,
+                "the rules of {rel_id} kept deducing new truths past the recursion limit of {limit}"
+            ),
+            RuntimeError::ConstraintViolated {
+                constraint,
+                bindings,
+            } => {
+                write!(f, "the constraint {constraint} is violated")?;
+                let shown = bindings
+                    .iter()
+                    .filter(|binding| !binding.is_empty())
+                    .take(5)
+                    .map(|binding| {
+                        binding
+                            .iter()
+                            .map(|(name, value)| format!("{name} = {value}"))
+                            .collect::<Vec<String>>()
+                            .join(", ")
+                    })
+                    .collect::<Vec<String>>();
+                if !shown.is_empty() {
+                    write!(f, " when {}", shown.join(" or when "))?;
+                }
+                if bindings.len() > shown.len() && !shown.is_empty() {
+                    write!(f, " and {} more", bindings.len() - shown.len())?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl From<String> for RuntimeError {
@@ -42,21 +133,82 @@ impl From<String> for RuntimeError {
     }
 }
 
+// what a line that ran successfully has to show. Rows are sorted and only hold true rows, the
+// undefined rows of the well founded model are in undefined
+#[derive(Debug, Clone, PartialEq)]
+pub enum LineOutcome {
+    Truths {
+        rel_id: RelId,
+        // names of the declared columns, empty when the relation isn't declared
+        columns: Vec<String>,
+        rows: Vec<Vec<Data>>,
+        undefined: Vec<Vec<Data>>,
+    },
+    Retracted(usize),
+    RuleRetracted,
+    Assumed,
+    Comment,
+}
+
+impl LineOutcome {
+    fn from_truths(rel_id: RelId, columns: Vec<String>, truths: &TruthList) -> Self {
+        let mut matrix = truths.to_vector();
+        matrix.sort();
+        let (undefined, rows): (Vec<Truth>, Vec<Truth>) = matrix
+            .into_iter()
+            .partition(|truth| truths.is_undefined(truth));
+        LineOutcome::Truths {
+            rel_id,
+            columns,
+            rows: rows
+                .iter()
+                .map(|truth| truth.get_data().to_owned())
+                .collect(),
+            undefined: undefined
+                .iter()
+                .map(|truth| truth.get_data().to_owned())
+                .collect(),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Engine {
     recursion_limit: usize,
+    well_founded: bool,
+    strict_relations: bool,
     tables: BTreeMap<RelId, Relation>,
+    // souffle `.decl`s, and the relations to write when a run ends
+    declarations: BTreeMap<RelId, Declaration>,
+    outputs: BTreeSet<RelId>,
+    // statements that must never hold, checked once a batch of changes is made
+    constraints: Vec<Constraint>,
+    fact_directory: PathBuf,
+    output_directory: PathBuf,
+    // where changes are persisted, engines opened on a directory have one
+    storage: Option<Storage>,
+    transaction: Option<Box<OpenTransaction>>,
 }
 
-use std::hash::Hash;
-impl Hash for Engine {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.recursion_limit.hash(state);
-        self.tables.hash(state);
-    }
+// the fact base as it was when the transaction began, and the changes to persist when it's
+// committed
+#[derive(Debug, Clone)]
+struct OpenTransaction {
+    // the relations changed by the transaction as they were before it changed them, None for
+    // the ones it created
+    tables: BTreeMap<RelId, Option<Relation>>,
+    declarations: BTreeMap<RelId, Declaration>,
+    outputs: BTreeSet<RelId>,
+    constraints: Vec<Constraint>,
+    changes: Vec<String>,
+    // something that is only persisted by a snapshot changed
+    snapshot: bool,
+    // opened by the engine to check the constraints on a whole run, it can't be committed or
+    // rolled back by hand
+    implicit: bool,
 }
 
-fn get_lines_from_chars(raw_commands: String) -> Result<Vec<Line>, String> {
+fn get_lines_from_chars(raw_commands: String) -> Result<Vec<(Line, Span)>, DlError> {
     let commands = String::from("\n") + &raw_commands;
 
     let lex_res = lexer::lex(&commands);
@@ -71,19 +223,25 @@ fn get_lines_from_chars(raw_commands: String) -> Result<Vec<Line>, String> {
                     printparse!("{:?}", ast_vec);
                     Ok(ast_vec)
                 }
-                Err(err) => Err(err.print(&lexic, &commands)),
+                Err(err) => Err(err.into_dl_error(&lexic)),
             }
         }
-        Err(e) => Err(e.print(&commands)),
+        Err(e) => Err(e.into()),
     }
 }
 
 impl fmt::Display for Engine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut ret = String::new();
+        for declaration in self.declarations.values() {
+            ret += &format!("{declaration}\n");
+        }
         for (_, table) in self.tables.iter() {
             ret += &format!("{table}");
         }
+        for constraint in self.constraints.iter() {
+            ret += &format!("{constraint}\n");
+        }
 
         write!(f, "{}", ret)
     }
@@ -93,7 +251,307 @@ impl Engine {
     pub fn new() -> Self {
         Self {
             recursion_limit: 5,
+            well_founded: false,
+            strict_relations: false,
+            tables: BTreeMap::new(),
+            declarations: BTreeMap::new(),
+            outputs: BTreeSet::new(),
+            constraints: vec![],
+            fact_directory: PathBuf::from("."),
+            output_directory: PathBuf::from("."),
+            storage: None,
+            transaction: None,
+        }
+    }
+
+    // an engine with the fact base kept in the directory, recovered from its last snapshot
+    // and the changes logged after it. Every change made from then on is persisted there
+    pub fn open(directory: impl Into<PathBuf>) -> Result<Self, DlError> {
+        let mut engine = Self::new();
+        engine.load(directory)?;
+        Ok(engine)
+    }
+
+    // replays the fact base stored in the directory and keeps storing the changes there. The
+    // replay runs with the settings the engine already has, like its recursion limit
+    pub fn load(&mut self, directory: impl Into<PathBuf>) -> Result<(), DlError> {
+        let (storage, snapshot, log) = Storage::open(&directory.into())?;
+
+        for source in [snapshot, log] {
+            if let Some(Err(err)) = self.run(source).pop() {
+                return Err(err);
+            }
+        }
+        self.storage = Some(storage);
+
+        // a transaction cut by a crash while it was logged, the new snapshot leaves it out
+        if self.transaction.is_some() {
+            self.undo_transaction();
+            self.snapshot()?;
+        }
+        Ok(())
+    }
+
+    // how many changes are logged before the fact base is snapshotted again
+    pub fn set_snapshot_interval(&mut self, lines: usize) {
+        if let Some(storage) = &mut self.storage {
+            storage.set_snapshot_interval(lines);
+        }
+    }
+
+    // writes the whole fact base and starts a new log, does nothing without storage. Inside a
+    // transaction it's taken when the transaction is committed
+    pub fn snapshot(&mut self) -> Result<(), DlError> {
+        if let Some(transaction) = &mut self.transaction {
+            transaction.snapshot = true;
+            return Ok(());
+        }
+        let exported = self.export();
+        match &mut self.storage {
+            Some(storage) => storage.snapshot(&exported),
+            None => Ok(()),
+        }
+    }
+
+    // logs the source of a change, the log is snapshotted when it gets long. Changes made in a
+    // transaction are logged together when it's committed
+    fn persist(&mut self, source: &str) -> Result<(), DlError> {
+        let Some(storage) = &mut self.storage else {
+            return Ok(());
+        };
+        if let Some(transaction) = &mut self.transaction {
+            transaction.changes.push(source.to_owned());
+            return Ok(());
+        }
+        if storage.append(source)? {
+            self.snapshot()?;
+        }
+        Ok(())
+    }
+
+    // keeps the fact base as it is until the transaction is committed or rolled back. Lines
+    // that fail inside it roll it back
+    pub fn begin(&mut self) -> Result<(), RuntimeError> {
+        if self.transaction.is_some() {
+            return Err(RuntimeError::Explanation(
+                "a transaction is already open, commit or rollback it first".into(),
+            ));
+        }
+        self.open_transaction(false);
+        Ok(())
+    }
+
+    // the changes are logged between `begin` and `commit`, a crash while they are written
+    // loses all of them. If they violate a constraint they are rolled back instead
+    pub fn commit(&mut self) -> Result<(), DlError> {
+        match &self.transaction {
+            Some(transaction) if !transaction.implicit => self.end_transaction(),
+            _ => Err(RuntimeError::Explanation("there's no transaction to commit".into()).into()),
+        }
+    }
+
+    pub fn rollback(&mut self) -> Result<(), RuntimeError> {
+        match &self.transaction {
+            Some(transaction) if !transaction.implicit => {
+                self.undo_transaction();
+                Ok(())
+            }
+            _ => Err(RuntimeError::Explanation(
+                "there's no transaction to roll back".into(),
+            )),
+        }
+    }
+
+    // for when the input ends, a transaction that was begun and never committed is rolled
+    // back and reported
+    pub fn end_input(&mut self) -> Result<(), RuntimeError> {
+        match &self.transaction {
+            Some(transaction) if !transaction.implicit => {
+                self.undo_transaction();
+                Err(RuntimeError::Explanation(
+                    "the input ended inside a transaction, its changes were rolled back".into(),
+                ))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn open_transaction(&mut self, implicit: bool) {
+        self.transaction = Some(Box::new(OpenTransaction {
             tables: BTreeMap::new(),
+            declarations: self.declarations.to_owned(),
+            outputs: self.outputs.to_owned(),
+            constraints: self.constraints.to_owned(),
+            changes: vec![],
+            snapshot: false,
+            implicit,
+        }));
+    }
+
+    // keeps the changes of the open transaction if they hold every constraint, otherwise
+    // they are undone
+    fn end_transaction(&mut self) -> Result<(), DlError> {
+        let touched: BTreeSet<RelId> = match &self.transaction {
+            Some(transaction) => transaction.tables.keys().cloned().collect(),
+            None => BTreeSet::new(),
+        };
+        if let Err(err) = self.check_constraints(&touched) {
+            self.undo_transaction();
+            return Err(err.into());
+        }
+        let Some(transaction) = self.transaction.take() else {
+            return Ok(());
+        };
+        if transaction.snapshot {
+            return self.snapshot();
+        }
+        match transaction.changes.as_slice() {
+            [] => Ok(()),
+            [change] => self.persist(change),
+            changes => self.persist(&format!("begin\n{}\ncommit", changes.join("\n"))),
+        }
+    }
+
+    fn undo_transaction(&mut self) {
+        if let Some(transaction) = self.transaction.take() {
+            for (rel_id, relation) in transaction.tables {
+                match relation {
+                    Some(relation) => self.tables.insert(rel_id, relation),
+                    None => self.tables.remove(&rel_id),
+                };
+            }
+            self.declarations = transaction.declarations;
+            self.outputs = transaction.outputs;
+            self.constraints = transaction.constraints;
+        }
+    }
+
+    // relations are only copied by the open transaction before it first changes them, so
+    // every change to a relation goes through here
+    fn get_table_mut(&mut self, rel_id: &RelId) -> Option<&mut Relation> {
+        self.remember_table(rel_id);
+        self.tables.get_mut(rel_id)
+    }
+
+    fn get_table_entry(&mut self, rel_id: &RelId) -> &mut Relation {
+        self.remember_table(rel_id);
+        self.tables
+            .entry(rel_id.to_owned())
+            .or_insert_with(|| Relation::new(rel_id))
+    }
+
+    fn remember_table(&mut self, rel_id: &RelId) {
+        if let Some(transaction) = &mut self.transaction {
+            if !transaction.tables.contains_key(rel_id) {
+                transaction
+                    .tables
+                    .insert(rel_id.to_owned(), self.tables.get(rel_id).cloned());
+            }
+        }
+    }
+
+    fn end_implicit_transaction(&mut self) -> Result<(), DlError> {
+        match &self.transaction {
+            Some(transaction) if transaction.implicit => self.end_transaction(),
+            _ => Ok(()),
+        }
+    }
+
+    // runs the changes as one batch that is checked against the constraints once they are all
+    // made, nothing is kept if any of them fails
+    fn batch<T>(
+        &mut self,
+        f: impl FnOnce(&mut Engine) -> Result<T, DlError>,
+    ) -> Result<T, DlError> {
+        if self.transaction.is_some() || self.constraints.is_empty() {
+            return f(self);
+        }
+        self.open_transaction(true);
+        match f(self) {
+            Ok(ret) => {
+                self.end_transaction()?;
+                Ok(ret)
+            }
+            Err(err) => {
+                self.undo_transaction();
+                Err(err)
+            }
+        }
+    }
+
+    // only the constraints that read one of the changed relations, directly or through rules,
+    // can have stopped holding
+    fn check_constraints(&self, touched: &BTreeSet<RelId>) -> Result<(), RuntimeError> {
+        for constraint in self.constraints.iter() {
+            let mut read = BTreeSet::new();
+            let mut pending = constraint.condition.get_dependencies();
+            while let Some(rel_id) = pending.pop() {
+                if let Some(relation) = self.tables.get(&rel_id) {
+                    if !read.contains(&rel_id) {
+                        pending.extend(relation.get_dependencies());
+                    }
+                }
+                read.insert(rel_id);
+            }
+            if !read.is_disjoint(touched) {
+                self.check_constraint(constraint)?;
+            }
+        }
+        Ok(())
+    }
+
+    // the values of the named variables for every way the condition holds, anonymous ones
+    // are left out
+    fn check_constraint(&self, constraint: &Constraint) -> Result<(), RuntimeError> {
+        let mut universe = VarContextUniverse::new();
+        universe.insert(VarContext::new());
+        let violations = self.complete_contexts(&constraint.condition, &universe)?;
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        let vars: Vec<String> = constraint
+            .condition
+            .get_vars()
+            .into_iter()
+            .filter(|name| !name.starts_with('_'))
+            .collect();
+        let mut bindings: Vec<Vec<(String, Data)>> = violations
+            .iter()
+            .map(|context| {
+                vars.iter()
+                    .filter_map(|name| context.get(name).map(|value| (name.to_owned(), value)))
+                    .collect()
+            })
+            .collect();
+        bindings.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        bindings.dedup();
+        Err(RuntimeError::ConstraintViolated {
+            constraint: constraint.to_string(),
+            bindings,
+        })
+    }
+
+    // runs the closure in a transaction, it's committed if the closure returns Ok and rolled
+    // back otherwise
+    pub fn transaction<T>(
+        &mut self,
+        f: impl FnOnce(&mut Engine) -> Result<T, DlError>,
+    ) -> Result<T, DlError> {
+        self.begin()?;
+        match f(self) {
+            Ok(ret) if self.transaction.is_some() => {
+                self.commit()?;
+                Ok(ret)
+            }
+            Ok(_) => Err(RuntimeError::Explanation(
+                "the transaction was closed by one of its lines".into(),
+            )
+            .into()),
+            Err(err) => {
+                self.undo_transaction();
+                Err(err)
+            }
         }
     }
 
@@ -101,87 +559,487 @@ impl Engine {
         self.recursion_limit = rl;
     }
 
+    // stored truths of the relation that lookups went through, to tell whether they used an index
+    #[cfg(test)]
+    pub(crate) fn get_candidates_read(&self, rel_id: &RelId) -> usize {
+        self.tables
+            .get(rel_id)
+            .map_or(0, |relation| relation.get_candidates_read())
+    }
+
+    // programs that can't be stratified are evaluated to their well founded model instead of
+    // being rejected, truths that are neither true nor false are returned as undefined
+    pub fn set_well_founded(&mut self, well_founded: bool) {
+        self.well_founded = well_founded;
+    }
+
+    // rule bodies read relations that were never defined as empty, unless relations are strict
+    pub fn set_strict_relations(&mut self, strict_relations: bool) {
+        self.strict_relations = strict_relations;
+    }
+
+    // where `.input rel` reads `rel.facts` from
+    pub fn set_fact_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.fact_directory = directory.into();
+    }
+
+    // where `.output rel` writes `rel.csv` to
+    pub fn set_output_directory(&mut self, directory: impl Into<PathBuf>) {
+        self.output_directory = directory.into();
+    }
+
     pub fn input(self: &mut Engine, commands: String) -> String {
-        let mut ret = String::new();
-        match get_lines_from_chars(commands) {
-            Ok(lines) => {
-                for line in lines {
-                    printdev!("\nexecuting: {}", line);
-
-                    match self.ingest_line(line) {
-                        Ok(Some(output)) => {
-                            let mut sorted_output = output.to_vector();
-                            sorted_output.sort();
-                            ret += &draw_table(sorted_output)
-                        }
-                        Ok(None) => (),
-                        Err(err) => {
-                            ret += &format!("An error ocurred on the execution step: \n {err:?}");
-                            break;
+        render_outcomes(
+            &self.run(commands.to_owned()),
+            &commands,
+            OutputFormat::Table,
+            true,
+        )
+    }
+
+    // outcome of every line, running stops at the first line that fails and rolls back the
+    // open transaction. The `.output` relations are written once every line ran, if there's no
+    // transaction left open
+    pub fn run(self: &mut Engine, commands: String) -> Vec<Result<LineOutcome, DlError>> {
+        let lines = match get_lines_from_chars(commands) {
+            Ok(lines) => lines,
+            Err(err) => return vec![Err(err)],
+        };
+
+        let mut ret = vec![];
+        for (line, span) in lines {
+            printdev!("\nexecuting: {}", line);
+
+            // with constraints the lines between transactions are checked together, so a batch
+            // can be valid as a whole while its lines aren't on their own
+            if matches!(line, Line::Transaction(_)) {
+                if let Err(err) = self.end_implicit_transaction() {
+                    ret.push(Err(err));
+                    return ret;
+                }
+            } else if self.transaction.is_none() && !self.constraints.is_empty() {
+                self.open_transaction(true);
+            }
+
+            // imported facts are persisted by a snapshot instead
+            let change = match &line {
+                Line::Assumption(_) | Line::Directive(Directive::Decl(_)) | Line::Constraint(_) => {
+                    Some(line.to_string())
+                }
+                Line::Query(_)
+                | Line::Directive(Directive::Input(_) | Directive::Output(_))
+                | Line::Transaction(_)
+                | Line::Comment(_) => None,
+            };
+            let outcome = self
+                .ingest_line(line)
+                .map_err(|error| DlError::Runtime {
+                    error,
+                    span: Some(span),
+                })
+                .and_then(|outcome| {
+                    if let Some(change) = change {
+                        self.persist(&change)?;
+                    }
+                    Ok(outcome)
+                });
+            let failed = outcome.is_err();
+            ret.push(outcome);
+            if failed {
+                // the lines before the failing one are kept unless they violate a constraint
+                match self
+                    .transaction
+                    .as_ref()
+                    .map(|transaction| transaction.implicit)
+                {
+                    Some(true) => {
+                        if let Err(err) = self.end_transaction() {
+                            ret.push(Err(err));
                         }
                     }
+                    Some(false) => self.undo_transaction(),
+                    None => (),
                 }
+                return ret;
+            }
+        }
+
+        if let Err(err) = self.end_implicit_transaction() {
+            ret.push(Err(err));
+            return ret;
+        }
+
+        if self.transaction.is_none() {
+            if let Err(err) = self.write_outputs() {
+                ret.push(Err(err));
             }
-            Err(err) => return err,
         }
         ret
     }
 
-    pub fn get_relation(&mut self, rel_id: RelId) -> Relation {
-        self.tables[&rel_id].to_owned()
+    // every `.output` relation with all the truths it holds, tab separated like souffle
+    // writes them
+    pub fn write_outputs(&self) -> Result<(), DlError> {
+        for rel_id in self.outputs.iter() {
+            let rows = self.export_relation(rel_id, OutputFormat::Tsv, true)?;
+            write(
+                self.output_directory
+                    .join(format!("{}.csv", rel_id.identifier)),
+                rows,
+            )?;
+        }
+        Ok(())
+    }
+
+    fn unknown_relation(&self, rel_id: RelId) -> RuntimeError {
+        RuntimeError::UnknownRelation(
+            rel_id.to_owned(),
+            self.tables
+                .keys()
+                .filter(|known| known.identifier == rel_id.identifier)
+                .cloned()
+                .collect(),
+        )
     }
 
-    pub fn query(
+    pub(crate) fn query(
         &self,
         query: &DeferedRelation,
         context: &VarContext,
         recursion_tally: &RecursionTally,
-    ) -> Result<TruthList, String> {
+    ) -> Result<TruthList, RuntimeError> {
         printprocess!("query {}", query);
 
-        let rel_id = query.get_rel_id();
-        let mut hypothetical_engine = self.clone();
+        match self.check_stratification() {
+            Ok(()) => self.fixpoint_query(query, context, recursion_tally, None),
+            Err(_) if self.well_founded => self.well_founded_query(query, context, recursion_tally),
+            Err(err) => Err(err.into()),
+        }
+    }
 
-        for assumption in &query.assumptions {
-            hypothetical_engine.ingest_assumption(assumption, context)?;
+    fn fixpoint_query(
+        &self,
+        query: &DeferedRelation,
+        context: &VarContext,
+        recursion_tally: &RecursionTally,
+        interpretation: Option<&Interpretation>,
+    ) -> Result<TruthList, RuntimeError> {
+        let mut table = AnswerTable::new(self.get_subsumable_relations());
+        if let Some(interpretation) = interpretation {
+            table.set_interpretation(interpretation.to_owned());
         }
 
-        Ok(hypothetical_engine
-            .get_relation(rel_id)
-            .get_filtered_truths(&query, &hypothetical_engine, recursion_tally)?)
+        loop {
+            table.start_round();
+            let truths = self.tabled_query(query, context, recursion_tally, &mut table)?;
+            if !table.has_grown() {
+                return Ok(truths);
+            }
+        }
+    }
+
+    // alternating fixpoint: evaluating the program with its negations read from an
+    // overestimate gives an underestimate and the other way around. Starting from the empty
+    // interpretation both converge to the true and the possible truths of the well founded model
+    fn well_founded_query(
+        &self,
+        query: &DeferedRelation,
+        context: &VarContext,
+        recursion_tally: &RecursionTally,
+    ) -> Result<TruthList, RuntimeError> {
+        let mut interpretations = vec![Interpretation::new()];
+
+        loop {
+            let mut next = Interpretation::new();
+            for rel_id in self.tables.keys() {
+                let all_truths = DeferedRelation::from((
+                    &rel_id.identifier,
+                    vec![Expresion::Literal(Data::Any); rel_id.column_count],
+                ));
+                next.insert(
+                    rel_id.to_owned(),
+                    self.fixpoint_query(
+                        &all_truths,
+                        &VarContext::new(),
+                        recursion_tally,
+                        interpretations.last(),
+                    )?,
+                );
+            }
+            interpretations.push(next);
+
+            // underestimates only grow and overestimates only shrink, so the same size means
+            // the same truths
+            let size = |interpretation: &Interpretation| {
+                interpretation
+                    .values()
+                    .map(|truths| truths.len())
+                    .sum::<usize>()
+            };
+            let count = interpretations.len();
+            if count > 3 && size(&interpretations[count - 1]) == size(&interpretations[count - 3]) {
+                break;
+            }
+        }
+
+        // interpretations alternate between over and underestimates, the first one is empty
+        let count = interpretations.len();
+        let (overestimate, underestimate) = if count % 2 == 0 {
+            (&interpretations[count - 1], &interpretations[count - 2])
+        } else {
+            (&interpretations[count - 2], &interpretations[count - 1])
+        };
+
+        let truths = self.fixpoint_query(query, context, recursion_tally, Some(overestimate))?;
+        let mut ret = truths.to_owned();
+        for truth in self
+            .fixpoint_query(query, context, recursion_tally, Some(underestimate))?
+            .into_iter()
+        {
+            if !truths.contains(&truth) {
+                ret.add_undefined(truth);
+            }
+        }
+        Ok(ret)
+    }
+
+    // negated and aggregated relations are evaluated to completion before they are read, so
+    // they are always evaluated before the rules that read them. That is only possible when no
+    // relation depends on its own negation or aggregate
+    fn check_stratification(&self) -> Result<(), String> {
+        for (rel_id, relation) in self.tables.iter() {
+            for stratified in relation.get_stratified_dependencies() {
+                if let Some(path) = self.find_dependency_path(&stratified, rel_id) {
+                    let cycle = path
+                        .iter()
+                        .map(|step| step.identifier.to_owned())
+                        .collect::<Vec<String>>()
+                        .join(" -> ");
+                    return Err(format!(
+                        "the program can't be stratified, {} depends on its own negation or aggregate: {} -> {}",
+                        rel_id.identifier, rel_id.identifier, cycle
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn find_dependency_path(&self, from: &RelId, to: &RelId) -> Option<Vec<RelId>> {
+        let mut parents: BTreeMap<RelId, RelId> = BTreeMap::new();
+        let mut pending = VecDeque::from([from.to_owned()]);
+
+        while let Some(current) = pending.pop_front() {
+            if &current == to {
+                let mut path = vec![current];
+                while let Some(parent) = parents.get(path.last()?) {
+                    path.push(parent.to_owned());
+                }
+                path.reverse();
+                return Some(path);
+            }
+
+            let Some(relation) = self.tables.get(&current) else {
+                continue;
+            };
+            for dependency in relation.get_dependencies() {
+                if dependency != *from && !parents.contains_key(&dependency) {
+                    parents.insert(dependency.to_owned(), current.to_owned());
+                    pending.push_back(dependency);
+                }
+            }
+        }
+        None
+    }
+
+    // answers of a call read under a negation or an aggregate, the called relation is evaluated
+    // to its own fixpoint first so they never see a partial table
+    pub(crate) fn complete_query(
+        &self,
+        query: &DeferedRelation,
+        context: &VarContext,
+        recursion_tally: &RecursionTally,
+        table: &mut AnswerTable,
+    ) -> Result<TruthList, RuntimeError> {
+        if !query.assumptions.is_empty() {
+            return self.tabled_query(query, context, recursion_tally, table);
+        }
+
+        let pattern = CallPattern::new(query);
+        let completed = match (table.get_interpretation(), table.get_completed(&pattern)) {
+            (Some(interpretation), _) => interpretation
+                .get(&query.get_rel_id())
+                .cloned()
+                .unwrap_or_else(TruthList::new),
+            (None, Some(truths)) => truths,
+            (None, None) => {
+                let truths = self.fixpoint_query(
+                    &pattern.to_filter(),
+                    &VarContext::new(),
+                    recursion_tally,
+                    None,
+                )?;
+                table.complete(pattern, truths.to_owned());
+                truths
+            }
+        };
+
+        let mut ret = TruthList::new();
+        for truth in completed.into_iter() {
+            if let Ok(fitted) = truth.fits_filter(query, VarContext::new()) {
+                ret.add(fitted);
+            }
+        }
+        Ok(ret)
+    }
+
+    // a relation is subsumable when it only has range restricted rules and only depends on
+    // subsumable relations, so any call to it can be answered filtering a more general one
+    fn get_subsumable_relations(&self) -> BTreeSet<RelId> {
+        let mut unsubsumable: BTreeSet<RelId> = self
+            .tables
+            .iter()
+            .filter(|(_, relation)| !relation.is_range_restricted())
+            .map(|(rel_id, _)| rel_id.to_owned())
+            .collect();
+
+        loop {
+            let starting_len = unsubsumable.len();
+            for (rel_id, relation) in self.tables.iter() {
+                if relation
+                    .get_dependencies()
+                    .iter()
+                    .any(|dependency| unsubsumable.contains(dependency))
+                {
+                    unsubsumable.insert(rel_id.to_owned());
+                }
+            }
+            if unsubsumable.len() == starting_len {
+                break;
+            }
+        }
+
+        self.tables
+            .keys()
+            .filter(|rel_id| !unsubsumable.contains(rel_id))
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn tabled_query(
+        &self,
+        query: &DeferedRelation,
+        context: &VarContext,
+        recursion_tally: &RecursionTally,
+        table: &mut AnswerTable,
+    ) -> Result<TruthList, RuntimeError> {
+        if !query.assumptions.is_empty() {
+            // assumptions only hold for this query, they are never persisted
+            let mut hypothetical_engine = self.clone();
+            hypothetical_engine.storage = None;
+
+            for assumption in &query.assumptions {
+                hypothetical_engine.ingest_assumption(assumption, context)?;
+            }
+
+            return hypothetical_engine.query(
+                &DeferedRelation {
+                    assumptions: vec![],
+                    ..query.to_owned()
+                },
+                context,
+                recursion_tally,
+            );
+        }
+
+        match self.tables.get(&query.get_rel_id()) {
+            Some(relation) => relation.get_filtered_truths(query, self, recursion_tally, table),
+            None if self.strict_relations => Err(self.unknown_relation(query.get_rel_id())),
+            None => Ok(TruthList::new()),
+        }
     }
 
     fn ingest_assumption(
         self: &mut Engine,
         assumption: &Assumption,
         context: &VarContext,
-    ) -> Result<(), String> {
+    ) -> Result<(), RuntimeError> {
         match assumption {
-            Assumption::Conditional(cond) => {
-                let rel_id = cond.get_rel_id();
-                let insertion_key = rel_id.clone();
+            Assumption::Conditional(cond) if cond.redefines => {
+                Ok(self.replace_rules(&cond.get_rel_id(), vec![cond.to_owned()])?)
+            }
+            Assumption::Conditional(cond) => Ok(self.insert_rule(cond.to_owned())?),
+            Assumption::Retraction(retraction) => {
+                self.retract(retraction, context)?;
+                Ok(())
+            }
+            Assumption::Update(update) => {
+                let filter = update.filter.clone_and_apply(context);
+                let matching = match self.tables.get(&filter.get_rel_id()) {
+                    Some(relation) => relation.get_matching_truths(&filter),
+                    None => vec![],
+                };
 
-                if let None = self.tables.get(&rel_id) {
-                    self.tables.insert(insertion_key, Relation::new(&rel_id));
+                // every goal is built before touching the relations, so a failing update
+                // changes nothing
+                let mut goals = vec![];
+                for (_, update_context) in matching.iter() {
+                    let mut datas = vec![];
+                    for exp in &update.goal.args {
+                        match exp.literalize(update_context) {
+                            Ok(data) => datas.push(data),
+                            Err(msg) => {
+                                return Err(format!(
+                                    "Cant update {} into {}: {msg}",
+                                    update.filter, update.goal
+                                )
+                                .into())
+                            }
+                        }
+                    }
+                    self.check_declared(&update.goal.rel_name, &datas)?;
+                    goals.push(InmediateRelation {
+                        negated: false,
+                        rel_name: update.goal.rel_name.to_owned(),
+                        args: datas,
+                    });
                 }
 
-                if let Some(table) = self.tables.get_mut(&rel_id) {
-                    table.add_conditional(cond.to_owned())?;
+                // goals can still be refused by the key of their relation
+                let touched = [filter.get_rel_id(), update.goal.get_rel_id()];
+                let before: Vec<Option<Relation>> = touched
+                    .iter()
+                    .map(|rel_id| self.tables.get(rel_id).cloned())
+                    .collect();
+
+                if let Some(relation) = self.get_table_mut(&filter.get_rel_id()) {
+                    for (truth, _) in matching.iter() {
+                        relation.remove_truth(truth);
+                    }
+                }
+                for goal in goals {
+                    if let Err(err) =
+                        self.ingest_assumption(&Assumption::RelationInmediate(goal), context)
+                    {
+                        for (rel_id, relation) in touched.into_iter().zip(before) {
+                            self.remember_table(&rel_id);
+                            match relation {
+                                Some(relation) => self.tables.insert(rel_id, relation),
+                                None => self.tables.remove(&rel_id),
+                            };
+                        }
+                        return Err(err);
+                    }
                 }
                 Ok(())
             }
-            Assumption::Update(_) => todo!(),
             Assumption::RelationInmediate(rel) => {
-                let rel_id = rel.get_rel_id();
-                let insertion_key = rel_id.clone();
-
-                if let None = self.tables.get(&rel_id) {
-                    self.tables.insert(insertion_key, Relation::new(&rel_id));
-                }
-
-                if let Some(relation) = self.tables.get_mut(&rel_id) {
-                    relation.add_truth(rel.to_owned())?;
+                if !rel.negated {
+                    self.check_declared(&rel.rel_name, &rel.args)?;
+                    self.make_room_for(rel)?;
                 }
+                self.get_table_entry(&rel.get_rel_id())
+                    .add_truth(rel.to_owned())?;
                 Ok(())
             }
             Assumption::RelationDefered(d_rel) => {
@@ -190,9 +1048,10 @@ impl Engine {
                     match exp.literalize(context) {
                         Ok(data) => datas.push(data),
                         Err(msg) => {
-                            return Err("Cant assume a relation with unliteralizable items: "
+                            return Err(("Cant assume a relation with unliteralizable items: "
                                 .to_string()
                                 + &msg)
+                                .into())
                         }
                     }
                 }
@@ -209,56 +1068,576 @@ impl Engine {
         }
     }
 
-    pub fn ingest_line(self: &mut Engine, line: Line) -> Result<Option<TruthList>, RuntimeError> {
-        match line {
-            Line::Query(q) => Ok(Some(self.query(
-                &q,
+    // the rules written in the source, like `path(x,y) :- edge(x,y)`. Anything else in it is
+    // an error
+    fn read_rules(&self, source: &str) -> Result<Vec<Conditional>, DlError> {
+        let mut rules = vec![];
+        for (mut line, span) in get_lines_from_chars(source.to_owned())? {
+            let runtime_error = |error: RuntimeError| DlError::Runtime {
+                error,
+                span: Some(span),
+            };
+            line.place_named_args(&self.declarations)
+                .map_err(|err| runtime_error(err.into()))?;
+            match line {
+                Line::Assumption(Assumption::Conditional(rule)) if !rule.redefines => {
+                    rules.push(rule)
+                }
+                line => {
+                    return Err(runtime_error(RuntimeError::Explanation(format!(
+                        "{line} isn't a rule written with :-"
+                    ))))
+                }
+            }
+        }
+        Ok(rules)
+    }
+
+    // adds the rules written in the source, none of them is kept if one can't be added
+    pub fn add_rule(&mut self, source: &str) -> Result<(), DlError> {
+        let rules = self.read_rules(source)?;
+        self.batch(|engine| {
+            for rule in rules {
+                engine
+                    .insert_rule(rule.to_owned())
+                    .map_err(RuntimeError::from)?;
+                engine.persist(&rule.to_string())?;
+            }
+            Ok(())
+        })
+    }
+
+    // removes the rules written in the source, they are found up to the names of their
+    // variables
+    pub fn retract_rule(&mut self, source: &str) -> Result<(), DlError> {
+        let rules = self.read_rules(source)?;
+        self.batch(|engine| {
+            for rule in rules {
+                engine.remove_rule(&rule).map_err(RuntimeError::from)?;
+                let retraction = Retraction {
+                    filter: rule.relation,
+                    guard: Some(Box::new(rule.conditional)),
+                };
+                engine.persist(&retraction.to_string())?;
+            }
+            Ok(())
+        })
+    }
+
+    // replaces every rule of the relation with the ones written in the source, its stored
+    // truths are kept. If any rule can't be added the relation is left as it was
+    pub fn redefine_rules(&mut self, rel_id: &RelId, source: &str) -> Result<(), DlError> {
+        let rules = self.read_rules(source)?;
+        self.batch(|engine| {
+            engine
+                .replace_rules(rel_id, rules)
+                .map_err(RuntimeError::from)?;
+            engine.snapshot()
+        })
+    }
+
+    // keys are only kept on asserted truths, so a relation with a key can't have rules
+    fn check_rule(&self, rule: &Conditional) -> Result<(), String> {
+        self.check_declared(&rule.relation.rel_name, &rule.relation.get_literals())?;
+        match self.get_declaration(&rule.relation.rel_name) {
+            Ok(declaration) if !declaration.key.is_empty() => Err(format!(
+                "{} is declared as {declaration}, a relation with a key can't have rules like {rule}",
+                rule.relation.rel_name
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn insert_rule(&mut self, rule: Conditional) -> Result<(), String> {
+        self.check_rule(&rule)?;
+        self.get_table_entry(&rule.get_rel_id())
+            .add_conditional(rule)
+    }
+
+    fn remove_rule(&mut self, rule: &Conditional) -> Result<(), String> {
+        let removed = match self.get_table_mut(&rule.get_rel_id()) {
+            Some(relation) => relation.remove_conditional(rule.to_owned()),
+            None => false,
+        };
+        match removed {
+            true => Ok(()),
+            false => Err(format!("the rule {rule} doesn't exist")),
+        }
+    }
+
+    fn replace_rules(&mut self, rel_id: &RelId, rules: Vec<Conditional>) -> Result<(), String> {
+        let mut relation = self
+            .tables
+            .get(rel_id)
+            .cloned()
+            .unwrap_or_else(|| Relation::new(rel_id));
+        relation.clear_conditionals();
+        for rule in rules {
+            if rule.get_rel_id() != *rel_id {
+                return Err(format!("the rule {rule} doesn't define {rel_id}"));
+            }
+            self.check_rule(&rule)?;
+            relation.add_conditional(rule)?;
+        }
+        self.remember_table(rel_id);
+        self.tables.insert(rel_id.to_owned(), relation);
+        Ok(())
+    }
+
+    // loads the rows of a csv or tsv file as truths of the relation. Nothing is imported if a
+    // row can't be, returns how many truths there were. The import is persisted as a snapshot
+    pub fn import_delimited(
+        &mut self,
+        rel_name: &str,
+        contents: &str,
+        options: &ImportOptions,
+    ) -> Result<usize, DlError> {
+        if !lexer::is_relation_name(rel_name) {
+            return Err(RuntimeError::Explanation(format!(
+                "{rel_name:?} isn't a valid relation name"
+            ))
+            .into());
+        }
+        let rows = read_rows(contents, options)?;
+        self.batch(|engine| {
+            for row in rows.iter() {
+                engine.ingest_assumption(
+                    &Assumption::RelationInmediate(InmediateRelation {
+                        negated: false,
+                        rel_name: rel_name.to_owned(),
+                        args: row.to_owned(),
+                    }),
+                    &VarContext::new(),
+                )?;
+            }
+            engine.snapshot()
+        })?;
+        Ok(rows.len())
+    }
+
+    // every truth and rule as source, one per line. Running it on a new engine gives back the
+    // same relations
+    pub fn export(&self) -> String {
+        format!("{self}")
+    }
+
+    // every rule of the queried relations with the order its conjuncts are read in, given the
+    // values the query binds
+    pub fn explain(&self, queries: &str) -> Result<String, DlError> {
+        let mut ret = String::new();
+        for (mut line, span) in get_lines_from_chars(queries.to_owned())? {
+            let runtime_error = |error: RuntimeError| DlError::Runtime {
+                error,
+                span: Some(span),
+            };
+            line.place_named_args(&self.declarations)
+                .map_err(|err| runtime_error(err.into()))?;
+            let Line::Query(query) = line else {
+                return Err(runtime_error(RuntimeError::Explanation(format!(
+                    "only queries can be explained, {line} isn't one"
+                ))));
+            };
+            let Some(relation) = self.tables.get(&query.get_rel_id()) else {
+                return Err(runtime_error(self.unknown_relation(query.get_rel_id())));
+            };
+
+            let plans = relation.get_join_plans(
+                &query,
+                self,
+                &AnswerTable::new(self.get_subsumable_relations()),
+            );
+            if plans.is_empty() {
+                ret += &format!(
+                    "{} has no rules, its stored truths are read\n",
+                    query.get_rel_id()
+                );
+            }
+            for (rule, plan) in plans {
+                ret += &format!("{rule}\n{plan}");
+            }
+        }
+        Ok(ret)
+    }
+
+    // `name/arity`, or just `name` when only one relation has that name
+    pub fn resolve_relation(&self, name: &str) -> Result<RelId, RuntimeError> {
+        if let Some((identifier, arity)) = name.rsplit_once('/') {
+            if let Ok(column_count) = arity.parse::<usize>() {
+                let rel_id = RelId {
+                    identifier: identifier.to_owned(),
+                    column_count,
+                };
+                if !self.tables.contains_key(&rel_id) {
+                    return Err(self.unknown_relation(rel_id));
+                }
+                return Ok(rel_id);
+            }
+        }
+
+        let named: Vec<RelId> = self
+            .tables
+            .keys()
+            .filter(|rel_id| rel_id.identifier == name)
+            .cloned()
+            .collect();
+        match named.as_slice() {
+            [rel_id] => Ok(rel_id.to_owned()),
+            [] => Err(RuntimeError::Explanation(format!(
+                "the relation {name} doesn't exist"
+            ))),
+            _ => Err(RuntimeError::Explanation(format!(
+                "there are {} relations named {name}, use {}",
+                named.len(),
+                named
+                    .iter()
+                    .map(|rel_id| rel_id.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" or ")
+            ))),
+        }
+    }
+
+    // the rows of a relation in the given format. Only the stored truths are written unless
+    // it's materialized, then the derived ones are written too, as a query would answer
+    pub fn export_relation(
+        &self,
+        rel_id: &RelId,
+        format: OutputFormat,
+        materialized: bool,
+    ) -> Result<String, DlError> {
+        let Some(relation) = self.tables.get(rel_id) else {
+            return Err(self.unknown_relation(rel_id.to_owned()).into());
+        };
+        let filter = DeferedRelation::from((
+            &rel_id.identifier,
+            vec![Expresion::Literal(Data::Any); rel_id.column_count],
+        ));
+
+        let truths = match materialized {
+            true => self.query(
+                &filter,
                 &VarContext::new(),
                 &RecursionTally::new(self.recursion_limit),
-            )?)),
-            Line::Assumption(assumption) => {
-                self.ingest_assumption(&assumption, &VarContext::new())?;
-                Ok(None)
+            )?,
+            false => {
+                let mut truths = TruthList::new();
+                for (truth, _) in relation.get_matching_truths(&filter) {
+                    truths.add(truth);
+                }
+                truths
             }
-            Line::Comment(_) => Ok(None),
+        };
+
+        let outcome = Ok(LineOutcome::from_truths(
+            rel_id.to_owned(),
+            self.get_column_names(rel_id),
+            &truths,
+        ));
+        Ok(render_outcome(&outcome, "", format, false))
+    }
+
+    fn get_column_names(&self, rel_id: &RelId) -> Vec<String> {
+        match self.declarations.get(rel_id) {
+            Some(declaration) => declaration
+                .columns
+                .iter()
+                .map(|(name, _)| name.to_owned())
+                .collect(),
+            None => vec![],
         }
     }
 
-    pub fn get_table(&self, rel_id: RelId) -> Option<&Relation> {
-        self.tables.get(&rel_id)
+    // the truths that have the key of the new one are removed when the relation is upserted,
+    // and the new one is refused when it's strict
+    fn make_room_for(&mut self, rel: &InmediateRelation) -> Result<(), String> {
+        let declaration = match self.get_declaration(&rel.rel_name) {
+            Ok(declaration) if !declaration.key.is_empty() => declaration.to_owned(),
+            _ => return Ok(()),
+        };
+        declaration.check_key(&rel.args)?;
+        let Some(relation) = self.get_table_mut(&rel.get_rel_id()) else {
+            return Ok(());
+        };
+
+        let filter = DeferedRelation::from((
+            &rel.rel_name,
+            declaration
+                .get_key_filter(&rel.args)
+                .into_iter()
+                .map(Expresion::Literal)
+                .collect(),
+        ));
+        let clashing: Vec<Truth> = relation
+            .get_matching_truths(&filter)
+            .into_iter()
+            .map(|(truth, _)| truth)
+            .filter(|truth| *truth.get_data() != rel.args)
+            .collect();
+        match (clashing.first(), declaration.key_mode) {
+            (None, _) => Ok(()),
+            (Some(truth), KeyMode::Strict) => Err(format!(
+                "{truth} already has the key of {rel}, {} is declared as {declaration}",
+                rel.rel_name
+            )),
+            (Some(_), KeyMode::Upsert) => {
+                for truth in clashing.iter() {
+                    relation.remove_truth(truth);
+                }
+                Ok(())
+            }
+        }
     }
-}
 
-fn draw_table(matrix: Vec<Truth>) -> String {
-    if matrix.len() == 0 {
-        return "\nEmpty Result\n".into();
+    // the row has to fit the declaration of the relation, if it has one. Columns that aren't
+    // known yet are `_`
+    pub(crate) fn check_declared(&self, rel_name: &str, row: &[Data]) -> Result<(), String> {
+        match self.get_declaration(rel_name) {
+            Ok(declaration) => declaration.check_row(row),
+            Err(_) => Ok(()),
+        }
     }
-    let mut ret = String::from("\n");
-    let column_count = matrix[0].get_width();
 
-    let col_width = matrix.iter().fold(vec![0; column_count], |acc, elm| {
-        let mut ret = acc.clone();
-        elm.get_data().iter().enumerate().for_each(|(i, e)| {
-            let e_size = e.to_string().len();
-            ret[i] = ret[i].max(e_size);
-        });
-        ret
-    });
+    fn get_declaration(&self, rel_name: &str) -> Result<&Declaration, RuntimeError> {
+        self.declarations
+            .values()
+            .find(|declaration| declaration.rel_name == rel_name)
+            .ok_or(RuntimeError::Explanation(format!(
+                "the relation {rel_name} isn't declared, it needs a .decl"
+            )))
+    }
 
-    for truth in matrix {
-        ret += &format!("(");
-        for (i, elm) in truth.get_data().iter().enumerate() {
-            let representation = elm.to_string();
-            ret += &format!("{representation}");
+    fn ingest_directive(&mut self, directive: &Directive) -> Result<(), RuntimeError> {
+        match directive {
+            Directive::Decl(declaration) => match self.get_declaration(&declaration.rel_name) {
+                Ok(declared)
+                    if declared.columns == declaration.columns
+                        && declared.key == declaration.key
+                        && declared.key_mode == declaration.key_mode => {}
+                Ok(declared) => {
+                    return Err(RuntimeError::Explanation(format!(
+                        "the relation {} was already declared as {declared}",
+                        declaration.rel_name
+                    )))
+                }
+                Err(_) => {
+                    // what the relation already holds has to fit the declaration
+                    for (rel_id, relation) in self.tables.iter() {
+                        if rel_id.identifier != declaration.rel_name {
+                            continue;
+                        }
+                        let mut keys = BTreeMap::new();
+                        for (truth, _) in relation.get_matching_truths(&DeferedRelation::from((
+                            &rel_id.identifier,
+                            vec![Expresion::Literal(Data::Any); rel_id.column_count],
+                        ))) {
+                            declaration.check_row(truth.get_data())?;
+                            if declaration.key.is_empty() {
+                                continue;
+                            }
+                            declaration.check_key(truth.get_data())?;
+                            let key: Vec<Data> = declaration
+                                .get_key_filter(truth.get_data())
+                                .into_iter()
+                                .filter(|data| !matches!(data, Data::Any))
+                                .collect();
+                            if let Some(other) = keys.insert(key, truth.to_owned()) {
+                                return Err(RuntimeError::Explanation(format!(
+                                    "{other} and {truth} have the same key, {} is declared as {declaration}",
+                                    declaration.rel_name
+                                )));
+                            }
+                        }
+                        for head in relation.get_rule_heads() {
+                            declaration.check_row(&head.get_literals())?;
+                            if !declaration.key.is_empty() {
+                                return Err(RuntimeError::Explanation(format!(
+                                    "{} has rules, a relation with a key can't have them",
+                                    declaration.rel_name
+                                )));
+                            }
+                        }
+                    }
+
+                    let rel_id = declaration.get_rel_id();
+                    self.get_table_entry(&rel_id);
+                    self.declarations.insert(rel_id, declaration.to_owned());
+                }
+            },
+            Directive::Input(rel_name) => {
+                let declaration = self.get_declaration(rel_name)?;
+                let mut options = ImportOptions::new(Delimiter::Tab);
+                options.column_types = Some(
+                    declaration
+                        .columns
+                        .iter()
+                        .map(|(_, data_type)| match data_type {
+                            DataType::Number => ColumnType::Number,
+                            DataType::String => ColumnType::String,
+                            DataType::Array => ColumnType::Auto,
+                        })
+                        .collect(),
+                );
 
-            for _ in 0..col_width[i] - representation.len() {
-                ret += &format!(" ");
+                let path = self.fact_directory.join(format!("{rel_name}.facts"));
+                let contents = read_to_string(&path).map_err(|err| {
+                    RuntimeError::Explanation(format!("couldn't read {}: {err}", path.display()))
+                })?;
+                self.import_delimited(rel_name, &contents, &options)
+                    .map_err(|err| {
+                        RuntimeError::Explanation(format!(
+                            "couldn't import {}, {err}",
+                            path.display()
+                        ))
+                    })?;
             }
-            if i != column_count - 1 {
-                ret += &format!(", ")
+            Directive::Output(rel_name) => {
+                let rel_id = self.get_declaration(rel_name)?.get_rel_id();
+                self.outputs.insert(rel_id);
             }
         }
-        ret += &format!(")\n");
+        Ok(())
+    }
+
+    // `!head(x) :- body(x)` removes that rule when the relation has it, otherwise it removes
+    // the stored truths matching the filter that hold the guard
+    fn retract(
+        &mut self,
+        retraction: &Retraction,
+        context: &VarContext,
+    ) -> Result<LineOutcome, RuntimeError> {
+        // a guarded retraction removes the rule it names, and otherwise the truths it guards.
+        // On a relation with rules it always names a rule, so a mistyped one isn't taken as
+        // the guard of its truths
+        let mut missing_rule = None;
+        if let Some(guard) = &retraction.guard {
+            let rule = Conditional {
+                relation: retraction.filter.to_owned(),
+                conditional: *guard.to_owned(),
+                redefines: false,
+            };
+            match self.remove_rule(&rule) {
+                Ok(()) => return Ok(LineOutcome::RuleRetracted),
+                Err(err) => missing_rule = Some(err),
+            }
+        }
+
+        let filter = retraction.filter.clone_and_apply(context);
+        let Some(relation) = self.tables.get(&filter.get_rel_id()) else {
+            return match missing_rule {
+                Some(err) => Err(err.into()),
+                None => Ok(LineOutcome::Retracted(0)),
+            };
+        };
+        if let Some(err) = missing_rule {
+            if relation.has_conditionals() {
+                return Err(format!(
+                    "{err}, the truths of {} can't be retracted with a guard since it has rules",
+                    filter.get_rel_id()
+                )
+                .into());
+            }
+        }
+
+        let mut retracted = vec![];
+        for (truth, truth_context) in relation.get_matching_truths(&filter) {
+            let holds = match &retraction.guard {
+                Some(guard) => {
+                    let mut universe = VarContextUniverse::new();
+                    universe.insert(truth_context.extend(context).unwrap_or(truth_context));
+                    !self.complete_contexts(guard, &universe)?.is_empty()
+                }
+                None => true,
+            };
+            if holds {
+                retracted.push(truth);
+            }
+        }
+        let relation = self
+            .get_table_mut(&filter.get_rel_id())
+            .unwrap_or_else(|| unreachable!());
+        for truth in retracted.iter() {
+            relation.remove_truth(truth);
+        }
+        Ok(LineOutcome::Retracted(retracted.len()))
+    }
+
+    // contexts of the statement over the universe, every relation it reads is evaluated to its
+    // fixpoint first
+    fn complete_contexts(
+        &self,
+        statement: &Statement,
+        universe: &VarContextUniverse,
+    ) -> Result<VarContextUniverse, RuntimeError> {
+        self.check_stratification()?;
+
+        let mut table = AnswerTable::new(self.get_subsumable_relations());
+        table.start_complete_reads();
+        statement.to_owned().memo_get_posible_contexts(
+            self,
+            &RecursionTally::new(self.recursion_limit),
+            &mut table,
+            universe,
+        )
+    }
+
+    pub(crate) fn ingest_line(
+        self: &mut Engine,
+        mut line: Line,
+    ) -> Result<LineOutcome, RuntimeError> {
+        line.place_named_args(&self.declarations)?;
+        match line {
+            Line::Query(q) => {
+                if q.assumptions.is_empty() && !self.tables.contains_key(&q.get_rel_id()) {
+                    return Err(self.unknown_relation(q.get_rel_id()));
+                }
+                let truths = self.query(
+                    &q,
+                    &VarContext::new(),
+                    &RecursionTally::new(self.recursion_limit),
+                )?;
+                Ok(LineOutcome::from_truths(
+                    q.get_rel_id(),
+                    self.get_column_names(&q.get_rel_id()),
+                    &truths,
+                ))
+            }
+            Line::Assumption(Assumption::Retraction(retraction)) => {
+                Ok(self.retract(&retraction, &VarContext::new())?)
+            }
+            Line::Assumption(assumption) => {
+                self.ingest_assumption(&assumption, &VarContext::new())?;
+                Ok(LineOutcome::Assumed)
+            }
+            Line::Directive(directive) => {
+                self.ingest_directive(&directive)?;
+                Ok(LineOutcome::Assumed)
+            }
+            Line::Transaction(TransactionControl::Begin) => {
+                self.begin()?;
+                Ok(LineOutcome::Assumed)
+            }
+            Line::Transaction(TransactionControl::Commit) => {
+                self.commit().map_err(|err| match err {
+                    DlError::Runtime { error, .. } => error,
+                    err => RuntimeError::Explanation(err.to_string()),
+                })?;
+                Ok(LineOutcome::Assumed)
+            }
+            Line::Transaction(TransactionControl::Rollback) => {
+                self.rollback()?;
+                Ok(LineOutcome::Assumed)
+            }
+            Line::Constraint(mut constraint) => {
+                constraint.condition.group_aggregates(&BTreeSet::new());
+                self.check_constraint(&constraint)?;
+                self.constraints.push(constraint);
+                Ok(LineOutcome::Assumed)
+            }
+            Line::Comment(_) => Ok(LineOutcome::Comment),
+        }
+    }
+
+    pub(crate) fn get_table(&self, rel_id: RelId) -> Option<&Relation> {
+        self.tables.get(&rel_id)
     }
-    ret
 }
@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use crate::parser::{
+    data_token::Data, defered_relation_token::DeferedRelation, expresion_token::Expresion, HasRelId,
+};
+
+use super::{relation::truth::Truth, truth_list::TruthList, var_context::VarContext, RelId};
+
+// the literal part of a relation call, unbound columns are None. Two calls with the same
+// pattern always deduce the same truths, so they share their answers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CallPattern {
+    rel_id: RelId,
+    bindings: Vec<Option<Data>>,
+}
+
+impl CallPattern {
+    pub fn new(filter: &DeferedRelation) -> Self {
+        Self {
+            rel_id: filter.get_rel_id(),
+            bindings: filter
+                .args
+                .iter()
+                .map(|exp| match exp.literalize(&VarContext::new()) {
+                    Ok(Data::Any) | Err(_) => None,
+                    Ok(data) => Some(data),
+                })
+                .collect(),
+        }
+    }
+
+    pub fn to_filter(&self) -> DeferedRelation {
+        DeferedRelation::from((
+            &self.rel_id.identifier,
+            self.bindings
+                .iter()
+                .map(|binding| Expresion::Literal(binding.to_owned().unwrap_or(Data::Any)))
+                .collect(),
+        ))
+    }
+
+    pub fn generalizes(&self, other: &CallPattern) -> bool {
+        self.rel_id == other.rel_id
+            && self
+                .bindings
+                .iter()
+                .zip(&other.bindings)
+                .all(|(general, specific)| general.is_none() || general == specific)
+    }
+
+    pub fn get_bindings(&self) -> &[Option<Data>] {
+        &self.bindings
+    }
+
+    pub fn admits(&self, truth: &Truth) -> bool {
+        truth
+            .get_data()
+            .iter()
+            .zip(&self.bindings)
+            .all(|(data, binding)| match (data, binding) {
+                (_, None) | (Data::Any, _) => true,
+                (data, Some(bound)) => data == bound,
+            })
+    }
+}
+
+// what a rule read on its runs: the patterns read by each literal of its condition. A literal
+// isn't read when the ones before it had no answers, so the reads of every run are kept.
+// Answers added to those tables after `started`, when the last run began, are the delta of the
+// next run
+#[derive(Debug, Clone)]
+pub struct RuleRun {
+    started: usize,
+    reads: BTreeSet<(usize, CallPattern)>,
+}
+
+impl RuleRun {
+    pub fn get_started(&self) -> usize {
+        self.started
+    }
+
+    pub fn get_read_patterns(&self) -> BTreeSet<CallPattern> {
+        self.reads
+            .iter()
+            .map(|(_, pattern)| pattern.to_owned())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TableEntry {
+    answers: Vec<(usize, Truth)>,
+    known: HashSet<Truth>,
+    visited_round: usize,
+    rule_runs: BTreeMap<usize, RuleRun>,
+}
+
+impl TableEntry {
+    fn since(&self, sequence: usize) -> impl Iterator<Item = &Truth> {
+        let start = self.answers.partition_point(|(added, _)| *added < sequence);
+        self.answers[start..].iter().map(|(_, truth)| truth)
+    }
+
+    fn last_added(&self) -> Option<usize> {
+        self.answers.last().map(|(added, _)| *added)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RuleFrame {
+    literal: usize,
+    delta: Option<(usize, usize)>,
+    reads: BTreeSet<(usize, CallPattern)>,
+}
+
+// truths of every relation, negations are read from it instead of from the relations
+pub type Interpretation = BTreeMap<RelId, TruthList>;
+
+// answers of every call pattern reached while evaluating a query. The query is reevaluated in
+// rounds until no table grows. A rule only runs again when a table it read got new answers,
+// and then once per grown literal, with that literal only reading the new answers. Calls to
+// subsumable relations are answered from the table of a more general call when there is one
+#[derive(Debug, Clone, Default)]
+pub struct AnswerTable {
+    entries: BTreeMap<CallPattern, TableEntry>,
+    round: usize,
+    sequence: usize,
+    grown: bool,
+    frames: Vec<RuleFrame>,
+    subsumable: BTreeSet<RelId>,
+    complete_reads: usize,
+    completed: BTreeMap<CallPattern, TruthList>,
+    interpretation: Option<Interpretation>,
+}
+
+impl AnswerTable {
+    pub fn new(subsumable: BTreeSet<RelId>) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            round: 0,
+            sequence: 0,
+            grown: false,
+            frames: vec![],
+            subsumable,
+            complete_reads: 0,
+            completed: BTreeMap::new(),
+            interpretation: None,
+        }
+    }
+
+    pub fn is_subsumable(&self, rel_id: &RelId) -> bool {
+        self.subsumable.contains(rel_id)
+    }
+
+    pub fn find_generalization(&self, pattern: &CallPattern) -> Option<CallPattern> {
+        if !self.is_subsumable(&pattern.rel_id) {
+            return None;
+        }
+        self.entries
+            .keys()
+            .find(|candidate| *candidate != pattern && candidate.generalizes(pattern))
+            .cloned()
+    }
+
+    pub fn start_round(&mut self) {
+        self.round += 1;
+        self.grown = false;
+    }
+
+    pub fn round(&self) -> usize {
+        self.round
+    }
+
+    pub fn has_grown(&self) -> bool {
+        self.grown
+    }
+
+    pub fn get_sequence(&self) -> usize {
+        self.sequence
+    }
+
+    pub fn contains(&self, pattern: &CallPattern) -> bool {
+        self.entries.contains_key(pattern)
+    }
+
+    // returns false if the pattern was already visited (or is being visited) on this round
+    pub fn visit(&mut self, pattern: &CallPattern) -> bool {
+        let round = self.round;
+        let entry = self.entry(pattern);
+        if entry.visited_round == round {
+            false
+        } else {
+            entry.visited_round = round;
+            true
+        }
+    }
+
+    pub fn knows(&self, pattern: &CallPattern, truth: &Truth) -> bool {
+        self.entries
+            .get(pattern)
+            .is_some_and(|entry| entry.known.contains(truth))
+    }
+
+    pub fn add(&mut self, pattern: &CallPattern, truth: Truth) {
+        let sequence = self.sequence;
+        let entry = self.entry(pattern);
+        if entry.known.insert(truth.to_owned()) {
+            entry.answers.push((sequence, truth));
+            self.sequence += 1;
+            self.grown = true;
+        }
+    }
+
+    pub fn read(&mut self, pattern: &CallPattern) -> TruthList {
+        let delta_since = match self.frames.last() {
+            Some(RuleFrame {
+                literal,
+                delta: Some((delta_literal, since)),
+                ..
+            }) if literal == delta_literal => *since,
+            _ => 0,
+        };
+
+        let mut ret = TruthList::new();
+        for truth in self.entry(pattern).since(delta_since) {
+            ret.add(truth.to_owned());
+        }
+
+        if let Some(frame) = self.frames.last_mut() {
+            frame.reads.insert((frame.literal, pattern.to_owned()));
+        }
+        ret
+    }
+
+    pub fn enter_literal(&mut self, literal: usize) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.literal = literal;
+        }
+    }
+
+    pub fn get_delta(&self) -> Option<(usize, usize)> {
+        self.frames.last().and_then(|frame| frame.delta)
+    }
+
+    // the delta literal only reads the answers added since the given sequence
+    pub fn start_rule(&mut self, delta: Option<(usize, usize)>) {
+        self.frames.push(RuleFrame {
+            literal: 0,
+            delta,
+            reads: BTreeSet::new(),
+        });
+    }
+
+    pub fn finish_rule(&mut self) -> BTreeSet<(usize, CallPattern)> {
+        self.frames
+            .pop()
+            .map(|frame| frame.reads)
+            .unwrap_or_default()
+    }
+
+    pub fn record_rule_run(
+        &mut self,
+        pattern: &CallPattern,
+        rule_index: usize,
+        started: usize,
+        reads: BTreeSet<(usize, CallPattern)>,
+    ) {
+        let run = self
+            .entry(pattern)
+            .rule_runs
+            .entry(rule_index)
+            .or_insert_with(|| RuleRun {
+                started,
+                reads: BTreeSet::new(),
+            });
+        run.started = started;
+        run.reads.extend(reads);
+    }
+
+    pub fn get_rule_run(&self, pattern: &CallPattern, rule_index: usize) -> Option<RuleRun> {
+        self.entries
+            .get(pattern)
+            .and_then(|entry| entry.rule_runs.get(&rule_index).cloned())
+    }
+
+    // literals of the run that read a table which got new answers after the run started
+    pub fn get_grown_literals(&self, run: &RuleRun) -> BTreeSet<usize> {
+        run.reads
+            .iter()
+            .filter(|(_, pattern)| {
+                self.entries
+                    .get(pattern)
+                    .and_then(|entry| entry.last_added())
+                    .is_some_and(|added| added >= run.started)
+            })
+            .map(|(literal, _)| *literal)
+            .collect()
+    }
+
+    // relations read under a negation or an aggregate are evaluated to completion before
+    // being read
+    pub fn start_complete_reads(&mut self) {
+        self.complete_reads += 1;
+    }
+
+    pub fn finish_complete_reads(&mut self) {
+        self.complete_reads -= 1;
+    }
+
+    pub fn reads_complete(&self) -> bool {
+        self.complete_reads > 0
+    }
+
+    pub fn get_completed(&self, pattern: &CallPattern) -> Option<TruthList> {
+        self.completed.get(pattern).cloned()
+    }
+
+    pub fn complete(&mut self, pattern: CallPattern, truths: TruthList) {
+        self.completed.insert(pattern, truths);
+    }
+
+    pub fn set_interpretation(&mut self, interpretation: Interpretation) {
+        self.interpretation = Some(interpretation);
+    }
+
+    pub fn get_interpretation(&self) -> Option<&Interpretation> {
+        self.interpretation.as_ref()
+    }
+
+    fn entry(&mut self, pattern: &CallPattern) -> &mut TableEntry {
+        self.entries
+            .entry(pattern.to_owned())
+            .or_insert_with(|| TableEntry {
+                answers: vec![],
+                known: HashSet::new(),
+                visited_round: 0,
+                rule_runs: BTreeMap::new(),
+            })
+    }
+}
@@ -0,0 +1,120 @@
+use std::{cmp::Reverse, collections::BTreeSet, fmt};
+
+// what the planner knows of a conjunct of an `&&` chain
+#[derive(Debug, Clone)]
+pub struct Conjunct {
+    pub text: String,
+    pub kind: ConjunctKind,
+    // every variable it mentions, and the ones it gives a value whenever it holds
+    pub vars: BTreeSet<String>,
+    pub binds: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ConjunctKind {
+    // a relation that can be read with any bindings. The variables of each argument that
+    // isn't `_`, and the truths it stores when it has no rules
+    Relation {
+        args: Vec<BTreeSet<String>>,
+        size: Option<usize>,
+    },
+    // an equality solves one of its sides once the other one is bound
+    Equality(BTreeSet<String>, BTreeSet<String>),
+    // comparisons, negations, aggregates, disjunctions and relations whose answers depend on
+    // the bindings they are called with wait for the variables other conjuncts can bind
+    Dependent,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PlanStep {
+    // position of the conjunct in the chain as it's written
+    pub conjunct: usize,
+    pub text: String,
+    // its variables that already have a value when it's read
+    pub bound: BTreeSet<String>,
+}
+
+// the order the conjuncts of an `&&` chain are read in, each one is read once over the
+// contexts given by the ones before it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct JoinPlan {
+    pub steps: Vec<PlanStep>,
+}
+
+impl fmt::Display for JoinPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, step) in self.steps.iter().enumerate() {
+            write!(f, " {}. {}", i + 1, step.text)?;
+            if !step.bound.is_empty() {
+                let bound: Vec<&str> = step.bound.iter().map(|var| var.as_str()).collect();
+                write!(f, " with {} bound", bound.join(", "))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl JoinPlan {
+    // greedy, at each step the ready conjunct that filters the most goes next. When none is
+    // ready the first one left as written goes
+    pub fn new(conjuncts: &[Conjunct], bound: &BTreeSet<String>) -> Self {
+        let mut bound = bound.to_owned();
+        let mut pending: Vec<usize> = (0..conjuncts.len()).collect();
+        let mut steps = vec![];
+
+        while !pending.is_empty() {
+            let next = pending
+                .iter()
+                .copied()
+                .filter(|i| is_ready(conjuncts, &pending, *i, &bound))
+                .min_by_key(|i| rank(&conjuncts[*i], &bound))
+                .unwrap_or(pending[0]);
+            pending.retain(|i| *i != next);
+
+            let conjunct = &conjuncts[next];
+            steps.push(PlanStep {
+                conjunct: next,
+                text: conjunct.text.to_owned(),
+                bound: conjunct.vars.intersection(&bound).cloned().collect(),
+            });
+            bound.extend(conjunct.binds.iter().cloned());
+        }
+
+        Self { steps }
+    }
+
+    pub fn get_order(&self) -> Vec<usize> {
+        self.steps.iter().map(|step| step.conjunct).collect()
+    }
+}
+
+fn is_ready(conjuncts: &[Conjunct], pending: &[usize], i: usize, bound: &BTreeSet<String>) -> bool {
+    let conjunct = &conjuncts[i];
+    match &conjunct.kind {
+        ConjunctKind::Relation { .. } => true,
+        ConjunctKind::Equality(vars_a, vars_b)
+            if vars_a.is_subset(bound) || vars_b.is_subset(bound) =>
+        {
+            true
+        }
+        ConjunctKind::Equality(_, _) | ConjunctKind::Dependent => pending
+            .iter()
+            .filter(|other| **other != i)
+            .flat_map(|other| conjuncts[*other].binds.iter())
+            .all(|var| !conjunct.vars.contains(var) || bound.contains(var)),
+    }
+}
+
+// filters go first, then relations by the arguments they have bound and by their size.
+// Relations with rules are taken as the biggest ones
+fn rank(conjunct: &Conjunct, bound: &BTreeSet<String>) -> (usize, Reverse<usize>, usize) {
+    match &conjunct.kind {
+        ConjunctKind::Relation { args, size } => (
+            1,
+            Reverse(args.iter().filter(|vars| vars.is_subset(bound)).count()),
+            size.unwrap_or(usize::MAX),
+        ),
+        ConjunctKind::Equality(_, _) | ConjunctKind::Dependent => (0, Reverse(0), 0),
+    }
+}
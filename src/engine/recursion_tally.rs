@@ -23,9 +23,19 @@ impl RecursionTally {
         }
     }
 
+    // generative rules can keep building new values forever, they are only reevaluated during
+    // the first max_recursion rounds of a fixpoint
+    pub fn allows_round(&self, round: usize) -> bool {
+        round <= self.max_recursion
+    }
+
+    pub fn get_limit(&self) -> usize {
+        self.max_recursion
+    }
+
     pub fn count_up(&mut self, rel_id: &RelId) {
         if let Some(depth_count) = self.tally.get_mut(rel_id) {
-            *depth_count -= 1;
+            *depth_count = depth_count.saturating_sub(1);
         } else {
             self.tally.insert(rel_id.to_owned(), self.max_recursion);
         }
@@ -1,18 +1,28 @@
-use std::{collections::HashSet, fmt, hash};
+use std::{
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+};
 mod conditional_truth;
 pub mod truth;
+mod truth_index;
 
 use print_macros::*;
 
 use crate::parser::{
-    conditional_token::Conditional, defered_relation_token::DeferedRelation,
-    inmediate_relation_token::InmediateRelation,
+    conditional_token::Conditional, data_token::Data, defered_relation_token::DeferedRelation,
+    expresion_token::Expresion, inmediate_relation_token::InmediateRelation,
 };
 
-use self::{conditional_truth::ConditionalTruth, truth::Truth};
+use self::{conditional_truth::ConditionalTruth, truth::Truth, truth_index::TruthIndex};
 
 use super::{
-    recursion_tally::RecursionTally, truth_list::TruthList, var_context::VarContext, Engine, RelId,
+    answer_table::{AnswerTable, CallPattern},
+    join_plan::JoinPlan,
+    recursion_tally::RecursionTally,
+    truth_list::TruthList,
+    var_context::VarContext,
+    Engine, RelId, RuntimeError,
 };
 
 #[derive(Debug, Clone)]
@@ -20,51 +30,120 @@ pub struct Relation {
     rel_id: RelId,
     truths: HashSet<Truth>,
     conditions: Vec<ConditionalTruth>,
+    // indexes of the truths by the columns lookups bind. One is built the first time a lookup
+    // binds its columns, and kept up to date from then on
+    indexes: RefCell<BTreeMap<Vec<usize>, TruthIndex>>,
+    // how many stored truths lookups went through
+    candidates_read: Cell<usize>,
 }
-use std::hash::Hash;
-impl Hash for Relation {
-    fn hash<H: hash::Hasher>(&self, state: &mut H) {
-        self.rel_id.hash(state);
-        self.truths
-            .iter()
-            .cloned()
-            .collect::<Vec<Truth>>()
-            .hash(state);
-        self.conditions
-            .iter()
-            .cloned()
-            .collect::<Vec<ConditionalTruth>>()
-            .hash(state);
-    }
-}
-
 impl Relation {
     pub fn new(rel_id: &RelId) -> Self {
         Self {
             rel_id: rel_id.to_owned(),
             truths: HashSet::new(),
             conditions: vec![],
+            indexes: RefCell::new(BTreeMap::new()),
+            candidates_read: Cell::new(0),
         }
     }
 
     pub fn add_truth(&mut self, rule: InmediateRelation) -> Result<(), String> {
+        let truth = Truth::from(&rule);
         match rule.negated {
             false => {
-                self.truths.insert(Truth::from(&rule));
+                if self.truths.insert(truth.to_owned()) {
+                    for index in self.indexes.get_mut().values_mut() {
+                        index.insert(&truth);
+                    }
+                }
             }
             true => {
-                let what_we_want_to_remove = &rule.args.to_owned();
-                self.truths
-                    .retain(|elm| !elm.afirms(what_we_want_to_remove));
+                self.remove_truth(&truth);
             }
         };
         Ok(())
     }
 
+    // calls f with every stored truth that can have the bound values, a lookup that binds no
+    // column goes through all of them
+    fn for_each_candidate(&self, bindings: &[Option<Data>], mut f: impl FnMut(&Truth)) {
+        let f = |truth: &Truth| {
+            self.candidates_read.set(self.candidates_read.get() + 1);
+            f(truth)
+        };
+        let (columns, values): (Vec<usize>, Vec<Data>) = bindings
+            .iter()
+            .enumerate()
+            .filter_map(|(i, binding)| match binding {
+                Some(Data::Any) | None => None,
+                Some(data) => Some((i, data.to_owned())),
+            })
+            .unzip();
+        if columns.is_empty() {
+            self.truths.iter().for_each(f);
+            return;
+        }
+
+        let mut indexes = self.indexes.borrow_mut();
+        indexes
+            .entry(columns.to_owned())
+            .or_insert_with(|| TruthIndex::new(columns, self.truths.iter()))
+            .get(&values)
+            .for_each(f);
+    }
+
+    #[cfg(test)]
+    pub fn get_candidates_read(&self) -> usize {
+        self.candidates_read.get()
+    }
+
+    // stored truths that match the filter, with the context that matches them
+    pub fn get_matching_truths(&self, filter: &DeferedRelation) -> Vec<(Truth, VarContext)> {
+        let bindings: Vec<Option<Data>> = filter
+            .args
+            .iter()
+            .map(|exp| match exp {
+                Expresion::Literal(data) => Some(data.to_owned()),
+                _ => None,
+            })
+            .collect();
+
+        let mut ret = vec![];
+        self.for_each_candidate(&bindings, |truth| {
+            let mut context = Some(VarContext::new());
+            for (data, exp) in truth.get_data().iter().zip(&filter.args) {
+                context = context.and_then(|context| exp.solve(data, &context).ok());
+            }
+            if let Some(context) = context {
+                ret.push((truth.to_owned(), context));
+            }
+        });
+        ret
+    }
+
+    pub fn remove_truth(&mut self, truth: &Truth) -> bool {
+        let removed = self.truths.remove(truth);
+        if removed {
+            for index in self.indexes.get_mut().values_mut() {
+                index.remove(truth);
+            }
+        }
+        removed
+    }
+
+    // rules are told apart by their shape, `_` isn't equal to itself as data so a derived
+    // comparison would never find a rule that reads it
+    fn find_conditional(&self, cond: &ConditionalTruth) -> Option<usize> {
+        let shape = cond.get_shape();
+        self.conditions
+            .iter()
+            .position(|conditional| conditional.get_shape() == shape)
+    }
+
     pub(crate) fn add_conditional(&mut self, cond: Conditional) -> Result<(), String> {
         if self
-            .conditions
-            .contains(&ConditionalTruth::from(cond.to_owned()))
+            .find_conditional(&ConditionalTruth::from(cond.to_owned()))
+            .is_some()
         {
             Err(format!(
                 "La condición {} ya existe dentro de la tabla {:?}",
@@ -77,48 +156,207 @@ impl Relation {
         }
     }
 
+    // false if the relation didn't have that rule
+    pub(crate) fn remove_conditional(&mut self, cond: Conditional) -> bool {
+        match self.find_conditional(&ConditionalTruth::from(cond)) {
+            Some(position) => {
+                self.conditions.remove(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn has_conditionals(&self) -> bool {
+        !self.conditions.is_empty()
+    }
+
+    pub(crate) fn get_rule_heads(&self) -> Vec<&DeferedRelation> {
+        self.conditions
+            .iter()
+            .map(|conditional| conditional.get_head())
+            .collect()
+    }
+
+    // the number of truths, when they are all stored
+    pub fn get_stored_size(&self) -> Option<usize> {
+        self.conditions.is_empty().then_some(self.truths.len())
+    }
+
+    // every rule with the plan it follows when the relation is read with the filter
+    pub(crate) fn get_join_plans(
+        &self,
+        filter: &DeferedRelation,
+        engine: &Engine,
+        table: &AnswerTable,
+    ) -> Vec<(String, JoinPlan)> {
+        self.conditions
+            .iter()
+            .map(|conditional| {
+                (
+                    conditional.to_string(),
+                    conditional.get_join_plan(filter, engine, table),
+                )
+            })
+            .collect()
+    }
+
+    pub(crate) fn clear_conditionals(&mut self) {
+        self.conditions.clear();
+    }
+
     fn get_all_truths(
-        self: &mut Relation,
+        &self,
         filter: &DeferedRelation,
         engine: &Engine,
         caller_recursion_tally: &RecursionTally,
-    ) -> Result<TruthList, String> {
-        let mut ret = TruthList::new();
-        let mut recursion_tally = caller_recursion_tally.to_owned();
+        table: &mut AnswerTable,
+    ) -> Result<TruthList, RuntimeError> {
+        let pattern = CallPattern::new(filter);
 
-        for literal_truth in self.truths.to_owned() {
-            ret.add(literal_truth);
+        if let Some(general_pattern) = table.find_generalization(&pattern) {
+            let mut ret = TruthList::new();
+            for truth in self
+                .get_all_truths(
+                    &general_pattern.to_filter(),
+                    engine,
+                    caller_recursion_tally,
+                    table,
+                )?
+                .into_iter()
+            {
+                if pattern.admits(&truth) {
+                    ret.add(truth);
+                }
+            }
+            return Ok(ret);
         }
-        recursion_tally.count_up(&self.rel_id);
 
-        if recursion_tally.go_deeper(&self.rel_id) {
-            for conditional in self.conditions.iter_mut() {
-                let sub_truth_list =
-                    conditional.get_deductions(filter, engine, &recursion_tally)?;
+        let known_pattern = table.contains(&pattern);
 
-                for truth in sub_truth_list.into_iter() {
-                    ret.add(truth);
+        if table.visit(&pattern) {
+            if !known_pattern {
+                self.for_each_candidate(pattern.get_bindings(), |literal_truth| {
+                    if pattern.admits(literal_truth) {
+                        table.add(&pattern, literal_truth.to_owned());
+                    }
+                });
+            }
+
+            let mut recursion_tally = caller_recursion_tally.to_owned();
+            recursion_tally.count_up(&self.rel_id);
+            let pattern_filter = pattern.to_filter();
+
+            // running a rule memoizes in its condition, the relation itself is left as it is
+            let mut conditions = self.conditions.to_owned();
+            for (i, conditional) in conditions.iter_mut().enumerate() {
+                // past the limit a generative rule only runs on what is already known, if it
+                // still deduces something new the answers would be short so it's an error
+                let capped = conditional.is_generative()
+                    && !(recursion_tally.go_deeper(&self.rel_id)
+                        && recursion_tally.allows_round(table.round()));
+                let limit_reached = || {
+                    printprocess!("** no more recursion **");
+                    RuntimeError::RecursionLimit(
+                        self.rel_id.to_owned(),
+                        recursion_tally.get_limit(),
+                    )
+                };
+
+                let deltas = match table.get_rule_run(&pattern, i) {
+                    None if capped => return Err(limit_reached()),
+                    None => vec![None],
+                    Some(run) => {
+                        if !capped {
+                            for read_pattern in run.get_read_patterns().iter() {
+                                engine.tabled_query(
+                                    &read_pattern.to_filter(),
+                                    &VarContext::new(),
+                                    &recursion_tally,
+                                    table,
+                                )?;
+                            }
+                        }
+
+                        let grown_literals = table.get_grown_literals(&run);
+                        if grown_literals.is_empty() {
+                            continue;
+                        } else if conditional.is_monotonic() {
+                            grown_literals
+                                .into_iter()
+                                .map(|literal| Some((literal, run.get_started())))
+                                .collect()
+                        } else {
+                            vec![None]
+                        }
+                    }
+                };
+
+                let started = table.get_sequence();
+                let mut reads = BTreeSet::new();
+
+                for delta in deltas {
+                    table.start_rule(delta);
+                    let deductions = conditional.get_deductions(
+                        &pattern_filter,
+                        engine,
+                        &recursion_tally,
+                        table,
+                    );
+                    reads.extend(table.finish_rule());
+
+                    for truth in deductions?.into_iter() {
+                        engine.check_declared(&self.rel_id.identifier, truth.get_data())?;
+                        if pattern.admits(&truth) {
+                            if capped && !table.knows(&pattern, &truth) {
+                                return Err(limit_reached());
+                            }
+                            table.add(&pattern, truth);
+                        }
+                    }
                 }
+
+                table.record_rule_run(&pattern, i, started, reads);
             }
-        } else {
-            printprocess!("** no more recursion **")
         }
-        Ok(ret)
+
+        Ok(table.read(&pattern))
+    }
+
+    pub fn is_range_restricted(&self) -> bool {
+        self.conditions
+            .iter()
+            .all(|conditional| conditional.is_range_restricted())
+    }
+
+    pub fn get_dependencies(&self) -> Vec<RelId> {
+        self.conditions
+            .iter()
+            .flat_map(|conditional| conditional.get_dependencies())
+            .collect()
+    }
+
+    pub fn get_stratified_dependencies(&self) -> Vec<RelId> {
+        self.conditions
+            .iter()
+            .flat_map(|conditional| conditional.get_stratified_dependencies())
+            .collect()
     }
 
     pub fn get_filtered_truths(
-        self: &mut Relation,
+        &self,
         filter: &DeferedRelation,
         engine: &Engine,
         recursion_tally: &RecursionTally,
-    ) -> Result<TruthList, String> {
+        table: &mut AnswerTable,
+    ) -> Result<TruthList, RuntimeError> {
         printprocess!(
             "get filtered truths of {} with filter {}",
             self.rel_id.identifier,
             filter
         );
 
-        let all_truths = self.get_all_truths(filter, engine, recursion_tally)?;
+        let all_truths = self.get_all_truths(filter, engine, recursion_tally, table)?;
 
         let mut matched_truths = TruthList::new();
 
@@ -135,11 +373,13 @@ impl Relation {
 impl fmt::Display for Relation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let mut ret = String::new();
-        for truth in self.truths.iter() {
-            ret += &format!("{truth}");
+        let mut truths: Vec<&Truth> = self.truths.iter().collect();
+        truths.sort();
+        for truth in truths {
+            ret += &format!("{truth}\n");
         }
         for condition in self.conditions.iter() {
-            ret += &format!("{condition}");
+            ret += &format!("{condition}\n");
         }
 
         write!(f, "{}", ret)
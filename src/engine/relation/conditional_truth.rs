@@ -4,11 +4,16 @@ use print_macros::*;
 
 use crate::{
     engine::{
-        recursion_tally::RecursionTally, truth_list::TruthList, var_context::VarContext,
-        var_context_universe::VarContextUniverse, Engine,
+        answer_table::AnswerTable, join_plan::JoinPlan, recursion_tally::RecursionTally,
+        truth_list::TruthList, var_context::VarContext, var_context_universe::VarContextUniverse,
+        Engine, RelId, RuntimeError,
     },
+    lexer::{self, LexogramType},
     parser::{
-        conditional_token::Conditional, data_token::Data, defered_relation_token::DeferedRelation,
+        conditional_token::Conditional,
+        data_token::Data,
+        defered_relation_token::DeferedRelation,
+        expresion_token::{Expresion, VarName},
         statement_token::Statement,
     },
 };
@@ -31,9 +36,38 @@ impl ConditionalTruth {
         filter: &DeferedRelation,
         engine: &Engine,
         recursion_tally: &RecursionTally,
-    ) -> Result<TruthList, String> {
+        table: &mut AnswerTable,
+    ) -> Result<TruthList, RuntimeError> {
         printprocess!("getting deductions of {}", self);
 
+        let mut posible_contexts = VarContextUniverse::new();
+        posible_contexts.insert(self.get_base_context(filter));
+
+        posible_contexts = self.condition.memo_get_posible_contexts(
+            engine,
+            recursion_tally,
+            table,
+            &posible_contexts,
+        )?;
+
+        printprocess!("* universe of {} is {}", self, posible_contexts);
+
+        let mut ret = TruthList::new();
+        for context in posible_contexts.iter() {
+            match self.template.to_truth(&context) {
+                Ok(truth) => {
+                    ret.add(truth);
+                }
+                Err(_) => (),
+            };
+        }
+
+        printprocess!("* truths of {} filtered by {} are {}", self, filter, ret);
+
+        Ok(ret)
+    }
+    // the values the filter gives to the variables of the head
+    fn get_base_context(&self, filter: &DeferedRelation) -> VarContext {
         let mut base_context = VarContext::new();
 
         for (filter, template) in filter.args.iter().zip(self.template.args.to_owned()) {
@@ -46,31 +80,101 @@ impl ConditionalTruth {
                 Err(_) => (),
             }
         }
+        base_context
+    }
 
-        let mut posible_contexts = VarContextUniverse::new();
-        posible_contexts.insert(base_context);
+    pub fn get_join_plan(
+        &self,
+        filter: &DeferedRelation,
+        engine: &Engine,
+        table: &AnswerTable,
+    ) -> JoinPlan {
+        self.condition.get_join_plan(
+            engine,
+            table,
+            &self.get_base_context(filter).get_bound_vars(),
+        )
+    }
 
-        posible_contexts =
-            self.condition
-                .memo_get_posible_contexts(engine, recursion_tally, &posible_contexts)?;
+    // a generative rule can deduce values that were not present in the truths it read
+    pub fn is_generative(&self) -> bool {
+        self.template.args.iter().any(|exp| exp.is_generative()) || self.condition.is_generative()
+    }
 
-        printprocess!("* universe of {} is {}", self, posible_contexts);
+    // a monotonic rule can be reevaluated reading only the new answers of one of its literals
+    pub fn is_monotonic(&self) -> bool {
+        self.condition.is_monotonic()
+    }
 
-        let mut ret = TruthList::new();
-        for context in posible_contexts.iter() {
-            match self.template.to_truth(&context) {
-                Ok(truth) => ret.add(truth),
-                Err(_) => (),
-            };
-        }
+    // every variable of a range restricted rule is bound by its condition, so the truths it
+    // deduces do not depend on the filter it is queried with
+    pub fn is_range_restricted(&self) -> bool {
+        let bound_vars = self.condition.get_bound_vars();
 
-        printprocess!("* truths of {} filtered by {} are {}", self, filter, ret);
+        !self.is_generative()
+            && !self.condition.has_assumptions()
+            && self.template.args.iter().all(|exp| match exp {
+                Expresion::Literal(_) => true,
+                Expresion::Var(VarName::Direct(name)) => bound_vars.contains(name),
+                _ => false,
+            })
+    }
 
-        Ok(ret)
+    // the source with its variables numbered by where they first show up, so rules that only
+    // differ in the names of their variables have the same shape. Named arguments are placed
+    // by then, so every identifier that isn't called is a variable
+    pub fn get_shape(&self) -> Vec<String> {
+        let source = self.to_string();
+        let Ok(lexograms) = lexer::lex(&source) else {
+            return vec![source];
+        };
+        let mut vars: Vec<String> = vec![];
+        lexograms
+            .iter()
+            .enumerate()
+            .map(|(i, lex)| match &lex.l_type {
+                LexogramType::Identifier(name)
+                    if !matches!(
+                        lexograms.get(i + 1).map(|next| &next.l_type),
+                        Some(LexogramType::LeftParenthesis)
+                    ) =>
+                {
+                    let position = vars.iter().position(|var| var == name).unwrap_or_else(|| {
+                        vars.push(name.to_owned());
+                        vars.len() - 1
+                    });
+                    format!("var {position}")
+                }
+                l_type => format!("{l_type:?}"),
+            })
+            .collect()
+    }
+
+    pub fn get_head(&self) -> &DeferedRelation {
+        &self.template
+    }
+
+    pub fn get_dependencies(&self) -> Vec<RelId> {
+        self.condition.get_dependencies()
+    }
+
+    pub fn get_stratified_dependencies(&self) -> Vec<RelId> {
+        self.condition.get_stratified_dependencies()
     }
+
     pub fn from(c: Conditional) -> Self {
+        let mut condition = c.conditional;
+        condition.number_literals(&mut 0);
+        condition.group_aggregates(
+            &c.relation
+                .args
+                .iter()
+                .flat_map(|exp| exp.get_vars())
+                .collect(),
+        );
+
         ConditionalTruth {
-            condition: c.conditional,
+            condition,
             template: c.relation,
         }
     }
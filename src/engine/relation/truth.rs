@@ -18,7 +18,7 @@ impl fmt::Display for Truth {
         let mut args = String::new();
         args += &"(";
         for (i, d) in self.data.iter().enumerate() {
-            args += &format!("{d}");
+            args += &d.to_source();
             if i != self.data.len() - 1 {
                 args += &",";
             }
@@ -45,9 +45,6 @@ impl Ord for Truth {
 }
 
 impl Truth {
-    pub fn afirms(&self, query: &Vec<Data>) -> bool {
-        query == &self.data
-    }
     pub fn get_data(&self) -> &Vec<Data> {
         &self.data
     }
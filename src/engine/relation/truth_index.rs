@@ -0,0 +1,75 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::parser::data_token::Data;
+
+use super::truth::Truth;
+
+// truths by the values of some of their columns. Truths with `_` in one of those columns
+// can have any value there, so they are returned by every lookup
+#[derive(Debug, Clone)]
+pub struct TruthIndex {
+    columns: Vec<usize>,
+    buckets: HashMap<Vec<Data>, HashSet<Truth>>,
+    wildcards: HashSet<Truth>,
+}
+
+impl TruthIndex {
+    pub fn new<'a>(columns: Vec<usize>, truths: impl Iterator<Item = &'a Truth>) -> Self {
+        let mut ret = Self {
+            columns,
+            buckets: HashMap::new(),
+            wildcards: HashSet::new(),
+        };
+        for truth in truths {
+            ret.insert(truth);
+        }
+        ret
+    }
+
+    fn get_key(&self, truth: &Truth) -> Option<Vec<Data>> {
+        let data = truth.get_data();
+        self.columns
+            .iter()
+            .map(|column| match &data[*column] {
+                Data::Any => None,
+                data => Some(data.to_owned()),
+            })
+            .collect()
+    }
+
+    pub fn insert(&mut self, truth: &Truth) {
+        match self.get_key(truth) {
+            Some(key) => self
+                .buckets
+                .entry(key)
+                .or_default()
+                .insert(truth.to_owned()),
+            None => self.wildcards.insert(truth.to_owned()),
+        };
+    }
+
+    pub fn remove(&mut self, truth: &Truth) {
+        match self.get_key(truth) {
+            Some(key) => {
+                if let Some(bucket) = self.buckets.get_mut(&key) {
+                    bucket.remove(truth);
+                    if bucket.is_empty() {
+                        self.buckets.remove(&key);
+                    }
+                }
+            }
+            None => {
+                self.wildcards.remove(truth);
+            }
+        }
+    }
+
+    // the truths that can have these values in the indexed columns
+    pub fn get(&self, values: &[Data]) -> impl Iterator<Item = &Truth> {
+        self.buckets
+            .get(values)
+            .into_iter()
+            .flatten()
+            .chain(self.wildcards.iter())
+    }
+}
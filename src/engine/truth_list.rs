@@ -5,6 +5,7 @@ use super::relation::truth::Truth;
 #[derive(Clone, Debug)]
 pub struct TruthList {
     truths: HashSet<Truth>,
+    undefined: HashSet<Truth>,
 }
 
 impl fmt::Display for TruthList {
@@ -23,6 +24,7 @@ impl TruthList {
     pub fn new() -> Self {
         Self {
             truths: HashSet::new(),
+            undefined: HashSet::new(),
         }
     }
 
@@ -34,7 +36,25 @@ impl TruthList {
         self.truths.clone().into_iter()
     }
 
-    pub fn add(&mut self, truth: Truth) {
-        self.truths.insert(truth);
+    pub fn add(&mut self, truth: Truth) -> bool {
+        self.truths.insert(truth)
+    }
+
+    // truths of the well founded model that are neither true nor false
+    pub fn add_undefined(&mut self, truth: Truth) -> bool {
+        self.undefined.insert(truth.to_owned());
+        self.truths.insert(truth)
+    }
+
+    pub fn is_undefined(&self, truth: &Truth) -> bool {
+        self.undefined.contains(truth)
+    }
+
+    pub fn contains(&self, truth: &Truth) -> bool {
+        self.truths.contains(truth)
+    }
+
+    pub fn len(&self) -> usize {
+        self.truths.len()
     }
 }
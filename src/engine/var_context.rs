@@ -1,5 +1,5 @@
 use crate::parser::data_token::Data;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
@@ -98,8 +98,12 @@ impl VarContext {
         ret
     }
 
-    pub fn len(&self) -> usize {
-        self.map.len()
+    pub fn get_bound_vars(&self) -> BTreeSet<String> {
+        self.map
+            .iter()
+            .filter(|(_, data)| !matches!(data, Data::Any))
+            .map(|(var, _)| var.to_owned())
+            .collect()
     }
 }
 
@@ -1,4 +1,7 @@
-use std::{collections::HashSet, fmt};
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt,
+};
 
 use print_macros::*;
 
@@ -53,31 +56,6 @@ impl VarContextUniverse {
         ret
     }
 
-    pub fn and(self, other: Self) -> Self {
-        // TODO check i use this
-        printprocess!("{} and {} =", self, other);
-
-        let mut contents = HashSet::new();
-
-        for context_a in &self.contents {
-            for content_b in &other.contents {
-                let op_merge = context_a.extend(&content_b);
-                match op_merge {
-                    Some(merged) => {
-                        contents.insert(merged);
-                    }
-                    None => (),
-                }
-            }
-        }
-
-        let ret = VarContextUniverse { contents };
-
-        printprocess!("{}", ret);
-
-        ret
-    }
-
     pub fn iter(&self) -> impl Iterator<Item = VarContext> {
         self.contents.to_owned().into_iter()
     }
@@ -86,6 +64,7 @@ impl VarContextUniverse {
         self.contents.insert(context);
     }
 
+    // removes every context that is extended by one of the contexts to remove
     pub fn difference(&self, contexts_to_remove: &Self) -> Self {
         let mut ret = HashSet::new();
 
@@ -93,7 +72,7 @@ impl VarContextUniverse {
             let copy_of_context = context.clone();
             if contexts_to_remove
                 .iter()
-                .find(move |e| e == &copy_of_context)
+                .find(move |e| &copy_of_context == e)
                 .is_none()
             {
                 ret.insert(context);
@@ -103,7 +82,21 @@ impl VarContextUniverse {
         Self { contents: ret }
     }
 
-    pub fn len(&self) -> usize {
-        self.contents.len()
+    // variables with a value in every context
+    pub fn get_bound_vars(&self) -> BTreeSet<String> {
+        let mut contexts = self.contents.iter();
+        let Some(first) = contexts.next() else {
+            return BTreeSet::new();
+        };
+        contexts.fold(first.get_bound_vars(), |bound, context| {
+            bound
+                .intersection(&context.get_bound_vars())
+                .cloned()
+                .collect()
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contents.is_empty()
     }
 }
@@ -0,0 +1,164 @@
+use std::{error, fmt, io};
+
+use crate::{engine::RuntimeError, utils::print_hilighted};
+
+// bytes of the source an error refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+// why the parser couldn't read something, with the reasons of every alternative it tried
+#[derive(Debug, Clone)]
+pub struct SyntaxFailure {
+    pub if_it_was: String,
+    pub failed_because: String,
+    pub span: Option<Span>,
+    pub causes: Vec<SyntaxFailure>,
+}
+
+#[derive(Debug)]
+pub enum DlError {
+    Lexer {
+        message: String,
+        span: Span,
+    },
+    Syntax(SyntaxFailure),
+    // the span is the one of the line that failed, when the error comes from running source
+    Runtime {
+        error: RuntimeError,
+        span: Option<Span>,
+    },
+    Io(io::Error),
+    // a row of a delimited file that couldn't be imported, rows are numbered by line
+    Import {
+        row: usize,
+        message: String,
+    },
+}
+
+impl From<RuntimeError> for DlError {
+    fn from(value: RuntimeError) -> Self {
+        Self::Runtime {
+            error: value,
+            span: None,
+        }
+    }
+}
+
+impl From<io::Error> for DlError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl fmt::Display for DlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DlError::Lexer { message, span } => write!(f, "{message} at {span}"),
+            DlError::Syntax(failure) => write!(f, "{}", failure.render(None, false, "")),
+            DlError::Runtime {
+                error,
+                span: Some(span),
+            } => write!(f, "{error} at {span}"),
+            DlError::Runtime { error, span: None } => write!(f, "{error}"),
+            DlError::Io(err) => write!(f, "{err}"),
+            DlError::Import { row, message } => write!(f, "row {row}: {message}"),
+        }
+    }
+}
+
+impl error::Error for DlError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            DlError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl DlError {
+    // the error as the repl shows it, underlining the source it refers to. Colors are ANSI
+    // escape codes
+    pub fn render(&self, source: &str, colored: bool) -> String {
+        match self {
+            DlError::Lexer { message, span } => format!(
+                "Lexer error breakdown: \n\"{message}\" at: {}",
+                hilight(source, span, "", colored)
+            ),
+            DlError::Syntax(failure) => failure.render(Some(source), colored, ""),
+            DlError::Runtime {
+                error,
+                span: Some(span),
+            } => {
+                let (bold, reset) = match colored {
+                    true => ("\x1b[1m", "\x1b[0m"),
+                    false => ("", ""),
+                };
+                format!(
+                    "An error ocurred on the execution step: \n{bold}{error}{reset} at:\n{}\n",
+                    hilight(source, span, "", colored)
+                )
+            }
+            DlError::Runtime { error, span: None } => {
+                format!("An error ocurred on the execution step: \n{error}")
+            }
+            DlError::Io(err) => format!("{err}"),
+            DlError::Import { .. } => format!("couldn't import {self}"),
+        }
+    }
+}
+
+impl SyntaxFailure {
+    fn render(&self, source: Option<&str>, colored: bool, indentation: &str) -> String {
+        let (bold, grey, reset) = match colored {
+            true => ("\x1b[1m", "\x1b[90m", "\x1b[0m"),
+            false => ("", "", ""),
+        };
+
+        let mut ret = format!(
+            "{indentation}Error trying to read a {bold}{}{reset} failed because:\n",
+            self.if_it_was,
+        );
+
+        if !self.causes.is_empty() {
+            for cause in self.causes.iter() {
+                ret += &cause.render(source, colored, &format!("{indentation}{grey}| {reset}"));
+            }
+        } else {
+            match (source, &self.span) {
+                (Some(source), Some(span)) => {
+                    ret += &format!(
+                    "{indentation}{bold}{}{reset} starting at:\n{indentation}{}\n{indentation}\n",
+                    self.failed_because,
+                    hilight(source, span, indentation, colored)
+                )
+                }
+                (None, Some(span)) => {
+                    ret += &format!("{indentation}{} at {span}\n", self.failed_because)
+                }
+                (_, None) => ret += &format!("{indentation}{}\n", self.failed_because),
+            }
+        };
+
+        ret
+    }
+}
+
+// lines are numbered as if the source started with a line break, like the lexer reads it
+fn hilight(source: &str, span: &Span, indentation: &str, colored: bool) -> String {
+    print_hilighted(
+        &format!("\n{source}"),
+        span.start + 1,
+        span.end + 1,
+        indentation.to_owned(),
+        colored,
+    )
+}
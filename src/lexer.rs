@@ -1,6 +1,7 @@
 use std::collections::BTreeMap;
 use std::io;
 
+use crate::error::DlError;
 use crate::utils::*;
 
 #[derive(Debug, Clone)]
@@ -26,6 +27,8 @@ pub enum LexogramType {
     OpAdd,
     OpSub,
     TrueWhen,
+    // a `:-` at the start of a line, it can only start a constraint
+    Denial,
     OpEq,
     OpNot,
     OpAnd,
@@ -39,6 +42,7 @@ pub enum LexogramType {
     Any,
     Query,
     Update,
+    Redefine,
     True,
 }
 #[derive(Debug, Clone)]
@@ -55,15 +59,12 @@ pub struct LexerError {
     msg: LexerErrorMsg,
 }
 
-impl LexerError {
-    pub fn print(&self, original_string: &String) -> String {
-        let ret = format!(
-            "Lexer error breakdown: \n\"{:?}\" at: {}",
-            self.msg,
-            print_hilighted(original_string, self.pos_s, self.pos_f, "".into())
-        );
-
-        ret
+impl From<LexerError> for DlError {
+    fn from(e: LexerError) -> Self {
+        DlError::Lexer {
+            message: format!("{:?}", e.msg),
+            span: source_span(e.pos_s, e.pos_f),
+        }
     }
 }
 
@@ -156,9 +157,35 @@ fn check_tail(pos_s: usize, tail: &str) -> Result<Option<Vec<Lexogram>>, LexerEr
     Ok(None)
 }
 
+// a name that is read back as one identifier, souffle directives are the ones with a dot
+pub fn is_relation_name(name: &str) -> bool {
+    match lex(&name.to_owned()).as_deref() {
+        Ok(
+            [Lexogram {
+                l_type: LexogramType::Identifier(identifier),
+                ..
+            }],
+        ) => identifier == name && !name.starts_with('.'),
+        _ => false,
+    }
+}
+
 pub fn lex(str: &String) -> Result<Vec<Lexogram>, LexerError> {
     let simple = simple_lexogram_analisis(str)?;
-    compound_lexogram_analisis(simple)
+    let mut ret = compound_lexogram_analisis(simple)?;
+
+    // so a fact on the line before a constraint isn't read as the head of a rule
+    for lexogram in ret.iter_mut() {
+        let starts_line = str.as_bytes()[..lexogram.pos_s]
+            .iter()
+            .rev()
+            .find(|c| !matches!(c, b' ' | b'\t' | b'\r'))
+            .is_none_or(|c| *c == b'\n');
+        if starts_line && matches!(lexogram.l_type, LexogramType::TrueWhen) {
+            lexogram.l_type = LexogramType::Denial;
+        }
+    }
+    Ok(ret)
 }
 
 fn compound_lexogram_analisis(simple: Vec<Lexogram>) -> Result<Vec<Lexogram>, LexerError> {
@@ -196,6 +223,12 @@ fn compound_lexogram_analisis(simple: Vec<Lexogram>) -> Result<Vec<Lexogram>, Le
                     l_type: LexogramType::CharEq,
                 }] => (),
 
+                [Lexogram {
+                    pos_f: _,
+                    pos_s: _,
+                    l_type: LexogramType::OpSub,
+                }] => (),
+
                 [Lexogram {
                     pos_f: _,
                     pos_s,
@@ -282,17 +315,30 @@ fn compound_lexogram_analisis(simple: Vec<Lexogram>) -> Result<Vec<Lexogram>, Le
                 }
 
                 [Lexogram {
-                    pos_f,
+                    pos_f: _,
                     pos_s,
                     l_type: LexogramType::CharColon,
-                }, _] => {
-                    return Err(LexerError {
-                        pos_s: *pos_s,
+                }, Lexogram {
+                    pos_f,
+                    pos_s: _,
+                    l_type: LexogramType::CharEq,
+                }] => {
+                    ret.push(Lexogram {
                         pos_f: *pos_f,
-                        msg: LexerErrorMsg::Custom(
-                            "compound lexogram analisis found a stray colon".into(),
-                        ),
-                    })
+                        pos_s: *pos_s,
+                        l_type: LexogramType::Redefine,
+                    });
+                    queue = vec![];
+                }
+
+                [colon @ Lexogram {
+                    pos_f: _,
+                    pos_s: _,
+                    l_type: LexogramType::CharColon,
+                }, next] => {
+                    ret.push(colon.clone());
+                    repeat_scan = true;
+                    queue = vec![next.clone()];
                 }
 
                 [any_lex] => {
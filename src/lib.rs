@@ -0,0 +1,19 @@
+// datalang as a library. The engine runs datalang programs and returns what each line did,
+// everything outside the reexports below is internal and may change between versions
+mod delimited;
+mod engine;
+mod error;
+mod lexer;
+mod parser;
+pub mod render;
+mod storage;
+mod tests;
+mod utils;
+
+pub use delimited::{ColumnType, Delimiter, ImportOptions};
+pub use engine::{Engine, LineOutcome, RelId, RuntimeError};
+pub use error::{DlError, Span, SyntaxFailure};
+pub use parser::data_token::Data;
+
+// the version of the public api above, see CHANGELOG.md
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -1,60 +1,227 @@
-pub mod engine;
-mod lexer;
-mod parser;
-mod tests;
-mod utils;
-
 use std::fs::write;
+use std::io::IsTerminal;
+use std::process::ExitCode;
 use std::{fs::read_to_string, io};
 
-use crate::engine::Engine;
-use crate::parser::error::ParserError;
+use datalang::render::{render_outcomes, OutputFormat, Renderer};
+use datalang::{ColumnType, Delimiter, DlError, Engine, ImportOptions, RelId, VERSION};
+
+const USAGE: &str = "usage:
+    datalang [options]                  repl, or runs stdin when it isn't a terminal
+    datalang [options] repl [file...]   repl, after running the files
+    datalang [options] run <file...>    runs the files
+    datalang [options] -e <program>     runs the program
+
+options:
+    --recursion-limit <n>               defaults to 4
+    --format <table|json|csv|tsv>       how answers are written, defaults to table
+    -F, --fact-dir <dir>                where .input reads facts from, defaults to .
+    -D, --output-dir <dir>              where .output writes to, defaults to .
+    --data-dir <dir>                    keeps the fact base in the directory across sessions
+    -V, --version                       prints the version";
 
-#[derive(Debug)]
-enum DLErr {
-    LexerError(lexer::LexerError),
-    IOError(std::io::Error),
-    ParserError(ParserError),
-    RuntimeError(engine::RuntimeError),
+enum Command {
+    Repl(Vec<String>),
+    Run(Vec<String>),
+    Eval(String),
+    Help,
+    Version,
 }
 
-impl From<lexer::LexerError> for DLErr {
-    fn from(e: lexer::LexerError) -> Self {
-        Self::LexerError(e)
-    }
+struct Options {
+    command: Command,
+    recursion_limit: usize,
+    format: OutputFormat,
+    fact_directory: String,
+    output_directory: String,
+    data_directory: Option<String>,
 }
 
-impl From<ParserError> for DLErr {
-    fn from(e: ParserError) -> Self {
-        Self::ParserError(e)
+fn parse_args(args: Vec<String>) -> Result<Options, String> {
+    let mut command = None;
+    let mut recursion_limit = 4;
+    let mut format = OutputFormat::Table;
+    let mut fact_directory = ".".to_string();
+    let mut output_directory = ".".to_string();
+    let mut data_directory = None;
+    let mut files = vec![];
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--recursion-limit" => {
+                let value = args
+                    .next()
+                    .ok_or("--recursion-limit needs a number".to_string())?;
+                recursion_limit = value
+                    .parse::<usize>()
+                    .map_err(|err| format!("invalid recursion limit {value}: {err}"))?;
+            }
+            "--format" => {
+                let value = args.next().ok_or("--format needs a format".to_string())?;
+                format = OutputFormat::from_name(&value).ok_or(format!(
+                    "unknown format {value}, use table, json, csv or tsv"
+                ))?;
+            }
+            "-F" | "--fact-dir" => {
+                fact_directory = args.next().ok_or(format!("{arg} needs a directory"))?;
+            }
+            "-D" | "--output-dir" => {
+                output_directory = args.next().ok_or(format!("{arg} needs a directory"))?;
+            }
+            "--data-dir" => {
+                data_directory = Some(args.next().ok_or("--data-dir needs a directory")?);
+            }
+            "-e" => {
+                let program = args.next().ok_or("-e needs a program".to_string())?;
+                match command {
+                    Some(Command::Eval(_)) => return Err("-e can only be given once".into()),
+                    Some(Command::Run(_) | Command::Repl(_)) => {
+                        return Err("-e can't be used with run or repl".into())
+                    }
+                    _ => command = Some(Command::Eval(program)),
+                }
+            }
+            "-h" | "--help" => command = Some(Command::Help),
+            "-V" | "--version" => command = Some(Command::Version),
+            "run" | "repl" if command.is_none() && files.is_empty() => {
+                command = Some(match arg.as_str() {
+                    "run" => Command::Run(vec![]),
+                    _ => Command::Repl(vec![]),
+                })
+            }
+            flag if flag.starts_with('-') => return Err(format!("unknown option {flag}")),
+            _ => files.push(arg),
+        }
     }
+
+    let command = match command {
+        Some(Command::Run(_)) if files.is_empty() => {
+            return Err("run needs at least one file".into())
+        }
+        Some(Command::Run(_)) => Command::Run(files),
+        Some(Command::Repl(_)) => Command::Repl(files),
+        Some(command) if files.is_empty() => command,
+        None if files.is_empty() => Command::Repl(vec![]),
+        _ => return Err(format!("unexpected arguments {}", files.join(" "))),
+    };
+
+    Ok(Options {
+        command,
+        recursion_limit,
+        format,
+        fact_directory,
+        output_directory,
+        data_directory,
+    })
 }
 
-impl From<engine::RuntimeError> for DLErr {
-    fn from(e: engine::RuntimeError) -> Self {
-        Self::RuntimeError(e)
+fn main() -> ExitCode {
+    let options = match parse_args(std::env::args().skip(1).collect()) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{err}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    // the stored fact base is replayed with the options already set
+    let mut engine = Engine::new();
+    engine.set_recursion_limit(options.recursion_limit);
+    engine.set_fact_directory(options.fact_directory);
+    engine.set_output_directory(options.output_directory);
+    if let Some(directory) = &options.data_directory {
+        if let Err(err) = engine.load(directory) {
+            eprintln!("the fact base in {directory} couldnt be opened: {err}");
+            return ExitCode::FAILURE;
+        }
     }
-}
 
-impl From<std::io::Error> for DLErr {
-    fn from(e: std::io::Error) -> Self {
-        Self::IOError(e)
+    let mut renderer = Renderer::new(options.format, io::stdout().is_terminal());
+    let result = match options.command {
+        Command::Help => {
+            println!("{USAGE}");
+            Ok(true)
+        }
+        Command::Version => {
+            println!("datalang {VERSION}");
+            Ok(true)
+        }
+        Command::Eval(program) => {
+            let succeeded = run_source(&mut engine, &mut renderer, program);
+            Ok(end_input(&mut engine) && succeeded)
+        }
+        Command::Run(files) => {
+            let succeeded = run_files(&mut engine, &mut renderer, &files);
+            Ok(end_input(&mut engine) && succeeded)
+        }
+        Command::Repl(files) if io::stdin().is_terminal() => {
+            run_files(&mut engine, &mut renderer, &files);
+            repl(&mut engine, options.format).map(|_| true)
+        }
+        Command::Repl(files) => {
+            let succeeded = run_files(&mut engine, &mut renderer, &files);
+            io::read_to_string(io::stdin())
+                .map(|source| run_source(&mut engine, &mut renderer, source) && succeeded)
+                .map(|succeeded| end_input(&mut engine) && succeeded)
+                .map_err(DlError::from)
+        }
+    };
+
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::FAILURE
+        }
     }
 }
-const AUTO_RUN: bool = true;
 
-fn main() -> Result<(), DLErr> {
+// prints what every line did, errors go to stderr. False if any line failed
+fn run_source(engine: &mut Engine, renderer: &mut Renderer, source: String) -> bool {
+    let mut succeeded = true;
+    for outcome in engine.run(source.to_owned()) {
+        let rendered = renderer.render(&outcome, &source);
+        match outcome {
+            Ok(_) => print!("{rendered}"),
+            Err(_) => {
+                eprintln!("{rendered}");
+                succeeded = false;
+            }
+        }
+    }
+    succeeded
+}
 
-    let mut engine = Engine::new();
+// false if the input left a transaction open, it's rolled back
+fn end_input(engine: &mut Engine) -> bool {
+    match engine.end_input() {
+        Ok(()) => true,
+        Err(err) => {
+            eprintln!("{err}");
+            false
+        }
+    }
+}
 
-    engine.set_recursion_limit(4);
+fn run_files(engine: &mut Engine, renderer: &mut Renderer, files: &[String]) -> bool {
+    let mut succeeded = true;
+    for file in files {
+        match read_to_string(file) {
+            Ok(source) => succeeded &= run_source(engine, renderer, source),
+            Err(err) => {
+                eprintln!("the file couldnt be read ({file}), reason: {err}");
+                return false;
+            }
+        }
+    }
+    succeeded
+}
 
+fn repl(engine: &mut Engine, mut format: OutputFormat) -> Result<(), DlError> {
     let stdin = io::stdin();
 
-    if AUTO_RUN {
-        println!("{}", engine.input(read_to_string("debug_input.dl")?));
-    }
-
     loop {
         let mut buffer = String::new();
 
@@ -62,13 +229,31 @@ fn main() -> Result<(), DLErr> {
         io::Write::flush(&mut io::stdout())
             .ok()
             .expect("Could not flush stdout");
-        stdin.read_line(&mut buffer)?;
+        if stdin.read_line(&mut buffer)? == 0 {
+            break;
+        }
 
         if buffer.chars().nth(0).unwrap_or_else(|| unreachable!()) == '/' {
             if buffer.starts_with("/exit") {
                 break;
             }
-            if buffer.starts_with("/import") {
+            if buffer.starts_with("/import_csv") || buffer.starts_with("/import_tsv") {
+                match read_import_command(&buffer) {
+                    Ok((rel_name, file_path, options)) => match read_to_string(&file_path) {
+                        Ok(contents) => {
+                            match engine.import_delimited(&rel_name, &contents, &options) {
+                                Ok(count) => println!("imported {count} truths into {rel_name}"),
+                                Err(err) => println!("{}", err.render(&contents, true)),
+                            }
+                        }
+                        Err(err) => {
+                            println!("the file couldnt be read ({file_path}), reason: {err}")
+                        }
+                    },
+                    Err(err) => println!("{err}"),
+                }
+            }
+            if buffer.starts_with("/import ") {
                 let file_path: String = buffer
                     .chars()
                     .into_iter()
@@ -76,25 +261,60 @@ fn main() -> Result<(), DLErr> {
                     .skip(1)
                     .collect();
                 match read_to_string(file_path.trim()) {
-                    Ok(commands) => println!("{}", engine.input(commands)),
+                    Ok(commands) => println!(
+                        "{}",
+                        render_outcomes(&engine.run(commands.to_owned()), &commands, format, true)
+                    ),
                     Err(err) => println!(
                         "the file couldnt be read ({}), reason: {err}",
                         file_path.trim()
                     ),
                 }
             }
-            if buffer.starts_with("/export") {
+            if buffer.starts_with("/export ") {
                 let file_path: String = buffer
                     .chars()
                     .into_iter()
                     .skip_while(|c| c != &' ')
                     .skip(1)
                     .collect();
-                match write(file_path.trim(), format!("{engine}")) {
+                match write(file_path.trim(), engine.export()) {
                     Ok(_) => println!("ok"),
                     Err(err) => println!("export failed due to: {err}"),
                 }
             }
+            if buffer.starts_with("/export_csv")
+                || buffer.starts_with("/export_tsv")
+                || buffer.starts_with("/export_json")
+            {
+                match read_export_command(engine, &buffer) {
+                    Ok((rel_id, file_path, export_format, materialized)) => {
+                        match engine
+                            .export_relation(&rel_id, export_format, materialized)
+                            .and_then(|rows| Ok(write(&file_path, rows)?))
+                        {
+                            Ok(_) => println!("ok"),
+                            Err(err) => println!("export failed due to: {err}"),
+                        }
+                    }
+                    Err(err) => println!("{err}"),
+                }
+            }
+
+            if buffer.starts_with("/explain ") {
+                let queries: String = buffer.chars().skip_while(|c| c != &' ').skip(1).collect();
+                match engine.explain(&queries) {
+                    Ok(plans) => print!("{plans}"),
+                    Err(err) => println!("{}", err.render(&queries, true)),
+                }
+            }
+
+            if buffer.starts_with("/snapshot") {
+                match engine.snapshot() {
+                    Ok(_) => println!("ok"),
+                    Err(err) => println!("snapshot failed due to: {err}"),
+                }
+            }
 
             if buffer.starts_with("/set_recursion_limit") {
                 let arg: String = buffer
@@ -103,15 +323,202 @@ fn main() -> Result<(), DLErr> {
                     .skip_while(|c| c != &' ')
                     .skip(1)
                     .collect();
-                match arg.parse::<usize>() {
+                match arg.trim().parse::<usize>() {
                     Ok(num) => engine.set_recursion_limit(num),
                     Err(err) => println!("error parsing argument: {err:?}"),
                 }
             }
+
+            if buffer.starts_with("/set_well_founded") {
+                let arg: String = buffer.chars().skip_while(|c| c != &' ').skip(1).collect();
+                match arg.trim().parse::<bool>() {
+                    Ok(well_founded) => engine.set_well_founded(well_founded),
+                    Err(err) => println!("error parsing argument: {err:?}"),
+                }
+            }
+
+            if buffer.starts_with("/format") {
+                let arg: String = buffer.chars().skip_while(|c| c != &' ').skip(1).collect();
+                match OutputFormat::from_name(arg.trim()) {
+                    Some(new_format) => format = new_format,
+                    None => println!("unknown format {}, use table, json, csv or tsv", arg.trim()),
+                }
+            }
+
+            if buffer.starts_with("/set_strict_relations") {
+                let arg: String = buffer.chars().skip_while(|c| c != &' ').skip(1).collect();
+                match arg.trim().parse::<bool>() {
+                    Ok(strict_relations) => engine.set_strict_relations(strict_relations),
+                    Err(err) => println!("error parsing argument: {err:?}"),
+                }
+            }
         } else {
-            println!("{}", engine.input(buffer));
+            println!(
+                "{}",
+                render_outcomes(&engine.run(buffer.to_owned()), &buffer, format, true)
+            );
         }
     }
 
     Ok(())
 }
+
+// `/import_csv <relation> <file> [header] [number,string,auto...]`
+fn read_import_command(buffer: &str) -> Result<(String, String, ImportOptions), String> {
+    let mut words = buffer.split_whitespace();
+    let mut options = match words.next() {
+        Some("/import_tsv") => ImportOptions::new(Delimiter::Tab),
+        _ => ImportOptions::new(Delimiter::Comma),
+    };
+    let usage = "usage: /import_csv <relation> <file> [header] [number,string,auto...]";
+    let rel_name = words.next().ok_or(usage)?.to_owned();
+    let file_path = words.next().ok_or(usage)?.to_owned();
+
+    for word in words {
+        match word {
+            "header" => options.header = true,
+            column_types => {
+                let mut types = vec![];
+                for name in column_types.split(',') {
+                    types.push(
+                        ColumnType::from_name(name)
+                            .ok_or(format!("unknown column type {name}, {usage}"))?,
+                    );
+                }
+                options.column_types = Some(types);
+            }
+        }
+    }
+    Ok((rel_name, file_path, options))
+}
+
+// `/export_csv <relation> <file> [materialized]`, the relation can be `name` or `name/arity`
+fn read_export_command(
+    engine: &Engine,
+    buffer: &str,
+) -> Result<(RelId, String, OutputFormat, bool), String> {
+    let mut words = buffer.split_whitespace();
+    let export_format = match words.next() {
+        Some("/export_tsv") => OutputFormat::Tsv,
+        Some("/export_json") => OutputFormat::Json,
+        _ => OutputFormat::Csv,
+    };
+    let usage = "usage: /export_csv <relation> <file> [materialized]";
+    let rel_name = words.next().ok_or(usage)?;
+    let file_path = words.next().ok_or(usage)?.to_owned();
+    let materialized = match words.next() {
+        Some("materialized") => true,
+        Some(word) => return Err(format!("unknown option {word}, {usage}")),
+        None => false,
+    };
+
+    let rel_id = engine
+        .resolve_relation(rel_name)
+        .map_err(|err| err.to_string())?;
+    Ok((rel_id, file_path, export_format, materialized))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()).collect())
+    }
+
+    #[test]
+    fn commands() {
+        assert!(
+            matches!(parse(&[]), Ok(Options { command: Command::Repl(files), .. }) if files.is_empty())
+        );
+        assert!(matches!(
+            parse(&["run", "a.dl", "b.dl"]),
+            Ok(Options { command: Command::Run(files), .. }) if files == ["a.dl", "b.dl"]
+        ));
+        assert!(matches!(
+            parse(&["repl", "a.dl"]),
+            Ok(Options { command: Command::Repl(files), .. }) if files == ["a.dl"]
+        ));
+        assert!(matches!(
+            parse(&["-e", "a(1) a(_)?"]),
+            Ok(Options { command: Command::Eval(program), .. }) if program == "a(1) a(_)?"
+        ));
+        assert!(matches!(
+            parse(&["--version"]),
+            Ok(Options {
+                command: Command::Version,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse(&["-h"]),
+            Ok(Options {
+                command: Command::Help,
+                ..
+            })
+        ));
+
+        assert_eq!(
+            Some("run needs at least one file".into()),
+            parse(&["run"]).err()
+        );
+        assert_eq!(
+            Some("unexpected arguments a.dl".into()),
+            parse(&["a.dl"]).err()
+        );
+        assert_eq!(
+            Some("unknown option --fast".into()),
+            parse(&["--fast"]).err()
+        );
+    }
+
+    #[test]
+    fn options() {
+        let options = parse(&[
+            "--recursion-limit",
+            "10",
+            "--format",
+            "csv",
+            "-F",
+            "facts",
+            "--output-dir",
+            "out",
+            "--data-dir",
+            "db",
+            "run",
+            "a.dl",
+        ])
+        .unwrap();
+        assert_eq!(10, options.recursion_limit);
+        assert_eq!(OutputFormat::Csv, options.format);
+        assert_eq!("facts", options.fact_directory);
+        assert_eq!("out", options.output_directory);
+        assert_eq!(Some("db".into()), options.data_directory);
+
+        let options = parse(&[]).unwrap();
+        assert_eq!(4, options.recursion_limit);
+        assert_eq!(OutputFormat::Table, options.format);
+        assert_eq!(".", options.fact_directory);
+        assert_eq!(None, options.data_directory);
+
+        assert!(parse(&["--recursion-limit", "many"]).is_err());
+        assert!(parse(&["--format", "xml"]).is_err());
+        assert_eq!(
+            Some("--data-dir needs a directory".into()),
+            parse(&["--data-dir"]).err()
+        );
+    }
+
+    #[test]
+    fn one_program() {
+        assert_eq!(
+            Some("-e can only be given once".into()),
+            parse(&["-e", "a(1)", "-e", "a(_)?"]).err()
+        );
+        assert_eq!(
+            Some("-e can't be used with run or repl".into()),
+            parse(&["run", "a.dl", "-e", "a(_)?"]).err()
+        );
+        assert_eq!(Some("-e needs a program".into()), parse(&["-e"]).err());
+    }
+}
@@ -1,20 +1,27 @@
+pub mod aggregate_token;
 pub mod assumption_token;
 pub mod conditional_token;
+pub mod constraint_token;
 pub mod data_token;
 pub mod defered_relation_token;
 pub mod destructuring_array_token;
+pub mod directive_token;
 pub mod expresion_token;
 pub mod inmediate_relation_token;
 pub mod line_token;
 pub mod list_token;
+pub mod retraction_token;
 pub mod statement_token;
+pub mod transaction_token;
 pub mod update_token;
 
 pub mod error;
 
 use crate::engine::RelId;
+use crate::error::Span;
 use crate::lexer;
 use crate::parser::error::FailureExplanation;
+use crate::utils::source_span;
 
 use self::error::ParserError;
 use self::line_token::*;
@@ -23,7 +30,8 @@ pub trait HasRelId {
     fn get_rel_id(&self) -> RelId;
 }
 
-pub fn parse(lexograms: &Vec<lexer::Lexogram>) -> Result<Vec<Line>, ParserError> {
+// every line with the span of the source it was read from
+pub fn parse(lexograms: &Vec<lexer::Lexogram>) -> Result<Vec<(Line, Span)>, ParserError> {
     let mut ret = vec![];
     let mut cursor = 0;
 
@@ -33,7 +41,8 @@ pub fn parse(lexograms: &Vec<lexer::Lexogram>) -> Result<Vec<Line>, ParserError>
         }
         match read_line(&lexograms, i)? {
             Ok((statement, jump_to)) => {
-                ret.push(statement);
+                let last = &lexograms[jump_to.max(i + 1) - 1];
+                ret.push((statement, source_span(lexograms[i].pos_s, last.pos_f)));
                 cursor = jump_to;
             }
             Err(e) => {
@@ -0,0 +1,219 @@
+use std::collections::BTreeSet;
+use std::fmt;
+
+use print_macros::*;
+
+use crate::engine::var_context::VarContext;
+use crate::lexer;
+use crate::lexer::LexogramType::*;
+use crate::parser::expresion_token::read_expresion;
+use crate::parser::statement_token::read_statement;
+
+use super::data_token::Data;
+use super::error::{FailureExplanation, ParserError};
+use super::expresion_token::Expresion;
+use super::statement_token::Statement;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl AggregateFunction {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "count" => Some(Self::Count),
+            "sum" => Some(Self::Sum),
+            "min" => Some(Self::Min),
+            "max" => Some(Self::Max),
+            "avg" => Some(Self::Avg),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for AggregateFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AggregateFunction::Count => write!(f, "count"),
+            AggregateFunction::Sum => write!(f, "sum"),
+            AggregateFunction::Min => write!(f, "min"),
+            AggregateFunction::Max => write!(f, "max"),
+            AggregateFunction::Avg => write!(f, "avg"),
+        }
+    }
+}
+
+// `count(edge(x,_))` or `sum(c : cost(x,c))`. The solutions of the statement are grouped by
+// the variables it shares with the rest of the rule, and each group is reduced to one value
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    pub target: Option<Expresion>,
+    pub statement: Box<Statement>,
+    pub group_vars: BTreeSet<String>,
+}
+
+impl fmt::Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.target {
+            Some(target) => write!(f, "{}({target} : {})", self.function, self.statement),
+            None => write!(f, "{}({})", self.function, self.statement),
+        }
+    }
+}
+
+impl Aggregate {
+    // None when the aggregate has no value for an empty group
+    pub fn reduce(&self, solutions: &Vec<VarContext>) -> Result<Option<Data>, String> {
+        let mut values = vec![];
+        if let Some(target) = &self.target {
+            for solution in solutions {
+                values.push(target.literalize(solution)?);
+            }
+        }
+
+        let sum = |values: &Vec<Data>| {
+            values.iter().try_fold(0.0, |acc, value| match value {
+                Data::Number(n) => Ok(acc + n),
+                _ => Err(format!(
+                    "can't {} {value}, it isn't a number",
+                    self.function
+                )),
+            })
+        };
+
+        Ok(match self.function {
+            AggregateFunction::Count if self.target.is_some() => Some(Data::Number(
+                values.iter().collect::<BTreeSet<&Data>>().len() as f64,
+            )),
+            AggregateFunction::Count => Some(Data::Number(solutions.len() as f64)),
+            AggregateFunction::Sum => Some(Data::Number(sum(&values)?)),
+            AggregateFunction::Avg if values.is_empty() => None,
+            AggregateFunction::Avg => Some(Data::Number(sum(&values)? / values.len() as f64)),
+            AggregateFunction::Min => values.into_iter().min(),
+            AggregateFunction::Max => values.into_iter().max(),
+        })
+    }
+}
+
+pub fn read_aggregate(
+    lexograms: &Vec<lexer::Lexogram>,
+    start_cursor: usize,
+) -> Result<Result<(Aggregate, usize), FailureExplanation>, ParserError> {
+    #[derive(Debug, Clone, Copy)]
+    enum AggregateParserStates {
+        SpectingFunction,
+        SpectingOpenParenthesis,
+        SpectingTargetOrStatement,
+        SpectingStatement,
+        SpectingClosingParenthesis,
+    }
+    use AggregateParserStates::*;
+
+    printparse!("read_aggregate at {}", start_cursor);
+
+    let mut cursor = start_cursor;
+    let mut state = SpectingFunction;
+
+    let mut op_function = None;
+    let mut op_target = None;
+    let mut op_statement = None;
+
+    for (i, lex) in lexograms.iter().enumerate() {
+        if cursor > i {
+            continue;
+        }
+
+        match (lex.l_type.to_owned(), state) {
+            (Identifier(name), SpectingFunction) => match AggregateFunction::from_name(&name) {
+                Some(function) => {
+                    op_function = Some(function);
+                    state = SpectingOpenParenthesis;
+                }
+                None => {
+                    return Ok(Err(FailureExplanation {
+                        lex_pos: i,
+                        if_it_was: "aggregate".into(),
+                        failed_because: format!("{name} is not an aggregate function"),
+                        parent_failure: vec![],
+                    }))
+                }
+            },
+            (LeftParenthesis, SpectingOpenParenthesis) => state = SpectingTargetOrStatement,
+            (_, SpectingTargetOrStatement | SpectingStatement) => {
+                if let SpectingTargetOrStatement = state {
+                    if let Ok((target, jump_to)) = read_expresion(lexograms, i, false)? {
+                        if let Some(CharColon) = lexograms.get(jump_to).map(|lex| &lex.l_type) {
+                            op_target = Some(target);
+                            cursor = jump_to + 1;
+                            state = SpectingStatement;
+                            continue;
+                        }
+                    }
+                }
+
+                match read_statement(lexograms, i)? {
+                    Ok((mut statement, jump_to)) => {
+                        statement.name_anonymous_vars(&mut 0);
+                        op_statement = Some(statement);
+                        cursor = jump_to;
+                        state = SpectingClosingParenthesis;
+                    }
+                    Err(e) => {
+                        return Ok(Err(FailureExplanation {
+                            lex_pos: i,
+                            if_it_was: "aggregate".into(),
+                            failed_because: "specting the aggregated statement".into(),
+                            parent_failure: vec![e],
+                        }))
+                    }
+                }
+            }
+            (RightParenthesis, SpectingClosingParenthesis) => {
+                return match (op_function, op_target, op_statement) {
+                    (Some(function), target, Some(statement))
+                        if target.is_some() || function == AggregateFunction::Count =>
+                    {
+                        Ok(Ok((
+                            Aggregate {
+                                function,
+                                target,
+                                statement: Box::new(statement),
+                                group_vars: BTreeSet::new(),
+                            },
+                            i + 1,
+                        )))
+                    }
+                    (Some(function), _, _) => Ok(Err(FailureExplanation {
+                        lex_pos: i,
+                        if_it_was: "aggregate".into(),
+                        failed_because: format!(
+                            "{function} needs the value to aggregate, like {function}(x : rel(x))"
+                        ),
+                        parent_failure: vec![],
+                    })),
+                    _ => unreachable!(),
+                };
+            }
+            _ => {
+                return Ok(Err(FailureExplanation {
+                    lex_pos: i,
+                    if_it_was: "aggregate".into(),
+                    failed_because: format!("pattern missmatch on {:#?} state", state),
+                    parent_failure: vec![],
+                }))
+            }
+        }
+    }
+    Ok(Err(FailureExplanation {
+        lex_pos: lexograms.len() - 1,
+        if_it_was: "aggregate".into(),
+        failed_because: "file ended".into(),
+        parent_failure: vec![],
+    }))
+}
@@ -1,13 +1,17 @@
 use core::fmt;
+use std::collections::BTreeMap;
 
 use super::{
     conditional_token::Conditional,
     defered_relation_token::{read_defered_relation, DeferedRelation},
+    directive_token::Declaration,
     error::{FailureExplanation, ParserError},
     inmediate_relation_token::{read_inmediate_relation, InmediateRelation},
+    retraction_token::{read_retraction, Retraction},
     update_token::{read_update, Update},
 };
 use crate::{
+    engine::RelId,
     lexer::{self},
     parser::conditional_token::read_conditional,
 };
@@ -18,6 +22,7 @@ pub enum Assumption {
     RelationDefered(DeferedRelation),
     Conditional(Conditional),
     Update(Update),
+    Retraction(Retraction),
 }
 
 impl fmt::Display for Assumption {
@@ -27,6 +32,34 @@ impl fmt::Display for Assumption {
             Assumption::RelationDefered(rel) => write!(f, "{rel}"),
             Assumption::Conditional(cond) => write!(f, "{cond}"),
             Assumption::Update(upd) => write!(f, "{upd}"),
+            Assumption::Retraction(retraction) => write!(f, "{retraction}"),
+        }
+    }
+}
+
+impl Assumption {
+    pub fn place_named_args(
+        &mut self,
+        declarations: &BTreeMap<RelId, Declaration>,
+    ) -> Result<(), String> {
+        match self {
+            Assumption::RelationInmediate(_) => Ok(()),
+            Assumption::RelationDefered(rel) => rel.place_named_args(declarations),
+            Assumption::Conditional(cond) => {
+                cond.relation.place_named_args(declarations)?;
+                cond.conditional.place_named_args(declarations)
+            }
+            Assumption::Update(update) => {
+                update.filter.place_named_args(declarations)?;
+                update.goal.place_named_args(declarations)
+            }
+            Assumption::Retraction(retraction) => {
+                retraction.filter.place_named_args(declarations)?;
+                match &mut retraction.guard {
+                    Some(guard) => guard.place_named_args(declarations),
+                    None => Ok(()),
+                }
+            }
         }
     }
 }
@@ -39,14 +72,20 @@ pub fn read_assumption(
     let b;
     let c;
     let d;
-    match read_inmediate_relation(lexograms, start_cursor)? {
-        Ok((i_rel, jump_to)) => return Ok(Ok((Assumption::RelationInmediate(i_rel), jump_to))),
-        Err(e) => a = e,
+    let e;
+    match read_retraction(lexograms, start_cursor)? {
+        Ok((ret, jump_to)) => return Ok(Ok((Assumption::Retraction(ret), jump_to))),
+        Err(err) => e = err,
     }
+    // a rule can have a literal head, so it's read before the fact its head would be
     match read_conditional(lexograms, start_cursor)? {
         Ok((ret, jump_to)) => return Ok(Ok((Assumption::Conditional(ret), jump_to))),
         Err(e) => b = e,
     }
+    match read_inmediate_relation(lexograms, start_cursor)? {
+        Ok((i_rel, jump_to)) => return Ok(Ok((Assumption::RelationInmediate(i_rel), jump_to))),
+        Err(e) => a = e,
+    }
     match read_update(lexograms, start_cursor)? {
         Ok((ret, jump_to)) => return Ok(Ok((Assumption::Update(ret), jump_to))),
         Err(e) => c = e,
@@ -60,6 +99,6 @@ pub fn read_assumption(
         lex_pos: start_cursor,
         if_it_was: "assumption".into(),
         failed_because: "wasnt any type of assumption".into(),
-        parent_failure: vec![a, b, c, d],
+        parent_failure: vec![e, a, b, c, d],
     }))
 }
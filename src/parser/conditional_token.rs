@@ -19,11 +19,16 @@ use super::HasRelId;
 pub struct Conditional {
     pub conditional: Statement,
     pub relation: DeferedRelation,
+    // `head(x) := body(x)` replaces every rule of the relation instead of adding one more
+    pub redefines: bool,
 }
 
 impl fmt::Display for Conditional {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} :- {}", self.relation, self.conditional)
+        match self.redefines {
+            true => write!(f, "{} := {}", self.relation, self.conditional),
+            false => write!(f, "{} :- {}", self.relation, self.conditional),
+        }
     }
 }
 
@@ -49,6 +54,7 @@ pub fn read_conditional(
 
     let mut cursor = start_cursor;
     let mut base_relation = None;
+    let mut redefines = false;
     let mut state = SpectingDeferedRelation;
 
     for (i, lex) in lexograms.iter().enumerate() {
@@ -72,6 +78,10 @@ pub fn read_conditional(
                 }
             },
             (TrueWhen, SpectingTrueWhen) => state = SpectingCondition,
+            (Redefine, SpectingTrueWhen) => {
+                redefines = true;
+                state = SpectingCondition;
+            }
             (_, SpectingCondition) => match (read_statement(lexograms, i)?, base_relation) {
                 (Err(e), _) => {
                     return Ok(Err(FailureExplanation {
@@ -86,6 +96,7 @@ pub fn read_conditional(
                         Conditional {
                             relation: def_rel,
                             conditional: cond,
+                            redefines,
                         },
                         jump_to,
                     )))
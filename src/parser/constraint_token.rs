@@ -0,0 +1,55 @@
+use std::fmt;
+
+use print_macros::*;
+
+use crate::lexer::{self, LexogramType::*};
+
+use super::error::{FailureExplanation, ParserError};
+use super::statement_token::{read_statement, Statement};
+
+// `:- age(x,a) && a < 0`, a statement that must never hold. Changes that make it hold are
+// refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    pub condition: Statement,
+}
+
+impl fmt::Display for Constraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ":- {}", self.condition)
+    }
+}
+
+pub fn read_constraint(
+    lexograms: &Vec<lexer::Lexogram>,
+    start_cursor: usize,
+) -> Result<Result<(Constraint, usize), FailureExplanation>, ParserError> {
+    printparse!("read_constraint at {}", start_cursor);
+
+    if !matches!(lexograms[start_cursor].l_type, TrueWhen | Denial) {
+        return Ok(Err(FailureExplanation {
+            lex_pos: start_cursor,
+            if_it_was: "constraint".into(),
+            failed_because: "specting :-".into(),
+            parent_failure: vec![],
+        }));
+    }
+
+    match lexograms.get(start_cursor + 1) {
+        Some(_) => match read_statement(lexograms, start_cursor + 1)? {
+            Ok((condition, jump_to)) => Ok(Ok((Constraint { condition }, jump_to))),
+            Err(e) => Ok(Err(FailureExplanation {
+                lex_pos: start_cursor + 1,
+                if_it_was: "constraint".into(),
+                failed_because: "specting statement".into(),
+                parent_failure: vec![e],
+            })),
+        },
+        None => Ok(Err(FailureExplanation {
+            lex_pos: start_cursor,
+            if_it_was: "constraint".into(),
+            failed_because: "file ended".into(),
+            parent_failure: vec![],
+        })),
+    }
+}
@@ -122,6 +122,64 @@ impl Data {
             Data::Any => "_".into(),
         }
     }
+
+    // the data as it's written in source. Strings escape their quotes and backslashes, and
+    // negative numbers are written as a substraction since there are no negative literals
+    pub fn to_source(&self) -> String {
+        match self {
+            Data::Number(n) if n.is_sign_negative() => format!("0-{}", -n),
+            Data::Number(n) => format!("{n}"),
+            Data::String(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Data::Array(arr) => {
+                "[".to_string()
+                    + &arr
+                        .iter()
+                        .map(|d| d.to_source())
+                        .collect::<Vec<String>>()
+                        .join(",")
+                    + "]"
+            }
+            Data::Any => "_".into(),
+        }
+    }
+
+    pub(crate) fn get_type(&self) -> Option<DataType> {
+        match self {
+            Data::Number(_) => Some(DataType::Number),
+            Data::String(_) => Some(DataType::String),
+            Data::Array(_) => Some(DataType::Array),
+            Data::Any => None,
+        }
+    }
+}
+
+// the type a declared column holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataType {
+    Number,
+    String,
+    Array,
+}
+
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::Number => write!(f, "number"),
+            DataType::String => write!(f, "string"),
+            DataType::Array => write!(f, "array"),
+        }
+    }
+}
+
+impl DataType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "number" => Some(Self::Number),
+            "string" => Some(Self::String),
+            "array" => Some(Self::Array),
+            _ => None,
+        }
+    }
 }
 
 pub fn read_data(
@@ -1,4 +1,5 @@
 use core::fmt;
+use std::collections::{BTreeMap, BTreeSet};
 use std::{hash, vec};
 
 use print_macros::*;
@@ -9,11 +10,16 @@ use crate::engine::RelId;
 use crate::lexer::LexogramType::*;
 use crate::parser::assumption_token::read_assumption;
 
-use crate::{lexer, parser::list_token::read_list};
+use crate::{
+    lexer,
+    parser::list_token::{read_list, read_named_list, NamedArgs},
+};
 
 use super::assumption_token::Assumption;
+use super::directive_token::Declaration;
 use super::error::ParserError;
 use super::{FailureExplanation, HasRelId};
+use crate::parser::data_token::Data;
 use crate::parser::expresion_token::Expresion;
 
 #[derive(Debug, Clone, Eq)]
@@ -22,6 +28,9 @@ pub struct DeferedRelation {
     pub assumptions: Vec<Assumption>,
     pub rel_name: String,
     pub args: Vec<Expresion>,
+    // `person(age: a)`, arguments given by the name of their column. They are placed in args
+    // once the declaration of the relation is known
+    pub named: NamedArgs,
 }
 
 impl PartialEq for DeferedRelation {
@@ -30,6 +39,7 @@ impl PartialEq for DeferedRelation {
             && self.assumptions.iter().eq(other.assumptions.iter())
             && self.rel_name == other.rel_name
             && self.args == other.args
+            && self.named == other.named
     }
 }
 
@@ -70,6 +80,62 @@ impl DeferedRelation {
         }
         DeferedRelation::from((&self.rel_name, literalized_vec))
     }
+
+    // `person(age: a)` as `person(_,a)`, by the columns of the relation's declaration. It's done
+    // in the relations of its assumptions too
+    pub fn place_named_args(
+        &mut self,
+        declarations: &BTreeMap<RelId, Declaration>,
+    ) -> Result<(), String> {
+        for assumption in self.assumptions.iter_mut() {
+            assumption.place_named_args(declarations)?;
+        }
+        if self.named.is_empty() {
+            return Ok(());
+        }
+
+        let Some(declaration) = declarations
+            .values()
+            .find(|declaration| declaration.rel_name == self.rel_name)
+        else {
+            return Err(format!(
+                "{self} names its columns, but {} isn't declared",
+                self.rel_name
+            ));
+        };
+        let mut args = vec![Expresion::Literal(Data::Any); declaration.columns.len()];
+        let mut placed = BTreeSet::new();
+        for (name, exp) in self.named.iter() {
+            match declaration
+                .columns
+                .iter()
+                .position(|(column, _)| column == name)
+            {
+                Some(i) if placed.insert(i) => args[i] = exp.to_owned(),
+                Some(_) => return Err(format!("{self} names the column {name} twice")),
+                None => {
+                    return Err(format!(
+                        "{} has no column {name}, it's declared as {declaration}",
+                        self.rel_name
+                    ))
+                }
+            }
+        }
+        self.args = args;
+        self.named.clear();
+        Ok(())
+    }
+
+    // the arguments that are literals, `_` for the ones only known once it's evaluated
+    pub fn get_literals(&self) -> Vec<Data> {
+        self.args
+            .iter()
+            .map(|exp| match exp {
+                Expresion::Literal(data) => data.to_owned(),
+                _ => Data::Any,
+            })
+            .collect()
+    }
 }
 
 impl fmt::Display for DeferedRelation {
@@ -83,18 +149,21 @@ impl fmt::Display for DeferedRelation {
                 args += &",";
             }
         }
+        args += &self
+            .named
+            .iter()
+            .map(|(name, exp)| format!("{name}: {exp}"))
+            .collect::<Vec<String>>()
+            .join(", ");
         args += &")";
 
         let mut assumptions = String::new();
-
-        assumptions += &"(";
         for (i, d) in self.assumptions.iter().enumerate() {
             assumptions += &format!("{d}");
             if i != self.assumptions.len() - 1 {
                 assumptions += &",";
             }
         }
-        assumptions += &")";
 
         let asumption_prefix = if self.assumptions.len() == 0 {
             "".to_string()
@@ -120,10 +189,34 @@ impl From<(&String, Vec<Expresion>)> for DeferedRelation {
             assumptions: vec![],
             rel_name: rel_name.to_owned(),
             args,
+            named: vec![],
         }
     }
 }
 
+// positional and named arguments, only one of them is given
+type Args = (Vec<Expresion>, NamedArgs);
+
+// `(a, b)` or `(name: a, age: b)`, told apart by the colon after the first name
+fn read_any_list(
+    lexograms: &Vec<lexer::Lexogram>,
+    start_cursor: usize,
+) -> Result<Result<(Args, usize), FailureExplanation>, ParserError> {
+    let names_columns = matches!(
+        (
+            lexograms.get(start_cursor + 1).map(|lex| &lex.l_type),
+            lexograms.get(start_cursor + 2).map(|lex| &lex.l_type),
+        ),
+        (Some(Identifier(_)), Some(CharColon))
+    );
+    Ok(match names_columns {
+        true => read_named_list(lexograms, start_cursor)?
+            .map(|(named, jump_to)| ((vec![], named), jump_to)),
+        false => read_list(lexograms, start_cursor, false)?
+            .map(|(args, jump_to)| ((args, vec![]), jump_to)),
+    })
+}
+
 pub fn read_defered_relation(
     lexograms: &Vec<lexer::Lexogram>,
     start_cursor: usize,
@@ -148,6 +241,7 @@ pub fn read_defered_relation(
     let mut negated = false;
     let mut op_rel_name = None;
     let mut args = vec![];
+    let mut named = vec![];
     let mut assumptions = vec![];
     let mut state = SpectingStatementIdentifierOrassumptionOrNegation;
 
@@ -200,7 +294,7 @@ pub fn read_defered_relation(
                 op_rel_name = Some(str);
                 state = SpectingStatementList;
             }
-            (_, SpectingStatementList) => match read_list(lexograms, i, false)? {
+            (_, SpectingStatementList) => match read_any_list(lexograms, i)? {
                 Err(e) => {
                     return Ok(Err(FailureExplanation {
                         lex_pos: i,
@@ -209,9 +303,10 @@ pub fn read_defered_relation(
                         parent_failure: (vec![e]),
                     }))
                 }
-                Ok((v, jump_to)) => {
+                Ok(((v, n), jump_to)) => {
                     cursor = jump_to;
                     args = v;
+                    named = n;
                     if check_querry {
                         state = SpectingQuery;
                     } else {
@@ -222,6 +317,7 @@ pub fn read_defered_relation(
                                     assumptions,
                                     rel_name,
                                     args,
+                                    named,
                                 },
                                 jump_to,
                             )));
@@ -239,6 +335,7 @@ pub fn read_defered_relation(
                             assumptions,
                             rel_name,
                             args,
+                            named,
                         },
                         i + 1,
                     )));
@@ -0,0 +1,317 @@
+use std::fmt;
+
+use print_macros::*;
+
+use crate::engine::RelId;
+use crate::lexer::{self, LexogramType::*};
+
+use super::data_token::{Data, DataType};
+use super::error::{FailureExplanation, ParserError};
+use super::HasRelId;
+
+// what a truth does when the relation already has one with its key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyMode {
+    // it's refused
+    Strict,
+    // it replaces the old one
+    Upsert,
+}
+
+impl fmt::Display for KeyMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeyMode::Strict => write!(f, "strict"),
+            KeyMode::Upsert => write!(f, "upsert"),
+        }
+    }
+}
+
+// `decl person(name: string, age: number, tags: array)`, the type of every column of a
+// relation. Souffle's `.decl edge(from:number, to:symbol)` declares them the same way.
+// `decl upsert salary(key emp: number, amount: number)` keeps one truth per employee
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Declaration {
+    pub rel_name: String,
+    pub columns: Vec<(String, DataType)>,
+    // names of the key columns, a relation without them can hold any truths
+    pub key: Vec<String>,
+    pub key_mode: KeyMode,
+    // read from a `.decl`, it's written back with souffle's types
+    pub souffle: bool,
+}
+
+impl fmt::Display for Declaration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let columns = self
+            .columns
+            .iter()
+            .map(|(name, data_type)| match (self.souffle, data_type) {
+                (true, DataType::String) => format!("{name}:symbol"),
+                (true, data_type) => format!("{name}:{data_type}"),
+                (false, data_type) if self.key.contains(name) => {
+                    format!("key {name}: {data_type}")
+                }
+                (false, data_type) => format!("{name}: {data_type}"),
+            })
+            .collect::<Vec<String>>()
+            .join(", ");
+        match (self.souffle, self.key.is_empty()) {
+            (true, _) => write!(f, ".decl {}({columns})", self.rel_name),
+            (false, true) => write!(f, "decl {}({columns})", self.rel_name),
+            (false, false) => write!(f, "decl {} {}({columns})", self.key_mode, self.rel_name),
+        }
+    }
+}
+
+impl Declaration {
+    // the row with `_` in the columns that aren't part of the key
+    pub fn get_key_filter(&self, row: &[Data]) -> Vec<Data> {
+        self.columns
+            .iter()
+            .zip(row)
+            .map(|((name, _), data)| match self.key.contains(name) {
+                true => data.to_owned(),
+                false => Data::Any,
+            })
+            .collect()
+    }
+
+    // a key has to name one truth, so its columns can't be `_`
+    pub fn check_key(&self, row: &[Data]) -> Result<(), String> {
+        for ((name, _), data) in self.columns.iter().zip(row) {
+            if self.key.contains(name) && matches!(data, Data::Any) {
+                return Err(format!(
+                    "the key column {name} can't be _, {} is declared as {self}",
+                    self.rel_name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // why the row can't be a truth of the relation, `_` fits any column
+    pub fn check_row(&self, row: &[Data]) -> Result<(), String> {
+        if row.len() != self.columns.len() {
+            return Err(format!(
+                "{} has {} columns, it's declared as {self}",
+                self.rel_name,
+                self.columns.len()
+            ));
+        }
+        for ((name, data_type), data) in self.columns.iter().zip(row) {
+            match data.get_type() {
+                Some(found) if found != *data_type => {
+                    return Err(format!(
+                        "the column {name} is a {data_type} but {} is a {found}, {} is declared as {self}",
+                        data.to_source(),
+                        self.rel_name
+                    ))
+                }
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HasRelId for Declaration {
+    fn get_rel_id(&self) -> RelId {
+        RelId {
+            identifier: self.rel_name.to_owned(),
+            column_count: self.columns.len(),
+        }
+    }
+}
+
+// declarations and souffle directives, `.input rel` reads `rel.facts` and `.output rel` writes
+// `rel.csv`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Directive {
+    Decl(Declaration),
+    Input(String),
+    Output(String),
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Directive::Decl(declaration) => write!(f, "{declaration}"),
+            Directive::Input(rel_name) => write!(f, ".input {rel_name}"),
+            Directive::Output(rel_name) => write!(f, ".output {rel_name}"),
+        }
+    }
+}
+
+fn read_souffle_type(name: &str) -> Option<DataType> {
+    match name {
+        "number" | "unsigned" | "float" => Some(DataType::Number),
+        "symbol" => Some(DataType::String),
+        _ => None,
+    }
+}
+
+pub fn read_directive(
+    lexograms: &[lexer::Lexogram],
+    start_cursor: usize,
+) -> Result<Result<(Directive, usize), FailureExplanation>, ParserError> {
+    #[derive(Debug, Clone, Copy)]
+    enum DirectiveParserStates {
+        SpectingDirective,
+        SpectingInputName,
+        SpectingOutputName,
+        SpectingKeyModeOrDeclName,
+        SpectingDeclName,
+        SpectingOpenParenthesis,
+        SpectingColumnNameOrClosingParenthesis,
+        SpectingColumnName,
+        SpectingColon,
+        SpectingColumnType,
+        SpectingComaOrClosingParenthesis,
+    }
+    use DirectiveParserStates::*;
+
+    printparse!("read_directive at {}", start_cursor);
+
+    let mut rel_name = String::new();
+    let mut columns = vec![];
+    let mut column_name = String::new();
+    let mut key = vec![];
+    let mut key_mode = KeyMode::Strict;
+    let mut souffle = true;
+    let mut state = SpectingDirective;
+
+    for (i, lex) in lexograms.iter().enumerate() {
+        if start_cursor > i {
+            continue;
+        }
+
+        match (lex.l_type.to_owned(), state) {
+            (Identifier(directive), SpectingDirective) if directive == ".decl" => {
+                state = SpectingDeclName
+            }
+            (Identifier(directive), SpectingDirective) if directive == "decl" => {
+                souffle = false;
+                state = SpectingKeyModeOrDeclName
+            }
+            // the mode is followed by the name, the name by the columns
+            (Identifier(mode), SpectingKeyModeOrDeclName)
+                if matches!(
+                    lexograms.get(i + 1).map(|lex| &lex.l_type),
+                    Some(Identifier(_))
+                ) =>
+            {
+                key_mode = match mode.as_str() {
+                    "strict" => KeyMode::Strict,
+                    "upsert" => KeyMode::Upsert,
+                    _ => {
+                        return Ok(Err(FailureExplanation {
+                            lex_pos: i,
+                            if_it_was: "directive".into(),
+                            failed_because: format!(
+                                "unknown key mode {mode}, specting strict or upsert"
+                            ),
+                            parent_failure: vec![],
+                        }))
+                    }
+                };
+                state = SpectingDeclName
+            }
+            (Identifier(directive), SpectingDirective) if directive == ".input" => {
+                state = SpectingInputName
+            }
+            (Identifier(directive), SpectingDirective) if directive == ".output" => {
+                state = SpectingOutputName
+            }
+            (Identifier(name), SpectingInputName) => {
+                return Ok(Ok((Directive::Input(name), i + 1)))
+            }
+            (Identifier(name), SpectingOutputName) => {
+                return Ok(Ok((Directive::Output(name), i + 1)))
+            }
+            (Identifier(name), SpectingDeclName | SpectingKeyModeOrDeclName) => {
+                rel_name = name;
+                state = SpectingOpenParenthesis
+            }
+            (LeftParenthesis, SpectingOpenParenthesis) => {
+                state = SpectingColumnNameOrClosingParenthesis
+            }
+            (Identifier(marker), SpectingColumnNameOrClosingParenthesis | SpectingColumnName)
+                if marker == "key"
+                    && !souffle
+                    && matches!(
+                        lexograms.get(i + 1).map(|lex| &lex.l_type),
+                        Some(Identifier(_))
+                    ) =>
+            {
+                if let Some(Identifier(name)) = lexograms.get(i + 1).map(|lex| &lex.l_type) {
+                    key.push(name.to_owned());
+                }
+                state = SpectingColumnName
+            }
+            (Identifier(name), SpectingColumnNameOrClosingParenthesis | SpectingColumnName) => {
+                column_name = name;
+                state = SpectingColon
+            }
+            (CharColon, SpectingColon) => state = SpectingColumnType,
+            (Identifier(type_name), SpectingColumnType) => {
+                let data_type = match souffle {
+                    true => read_souffle_type(&type_name),
+                    false => DataType::from_name(&type_name),
+                };
+                match data_type {
+                    Some(data_type) => {
+                        columns.push((column_name.to_owned(), data_type));
+                        state = SpectingComaOrClosingParenthesis
+                    }
+                    None => {
+                        return Ok(Err(FailureExplanation {
+                            lex_pos: i,
+                            if_it_was: "directive".into(),
+                            failed_because: match souffle {
+                                true => format!(
+                                    "unknown type {type_name}, specting number, unsigned, float or symbol"
+                                ),
+                                false => format!(
+                                    "unknown type {type_name}, specting string, number or array"
+                                ),
+                            },
+                            parent_failure: vec![],
+                        }))
+                    }
+                }
+            }
+            (Coma, SpectingComaOrClosingParenthesis) => state = SpectingColumnName,
+            (
+                RightParenthesis,
+                SpectingComaOrClosingParenthesis | SpectingColumnNameOrClosingParenthesis,
+            ) => {
+                return Ok(Ok((
+                    Directive::Decl(Declaration {
+                        rel_name,
+                        columns,
+                        key,
+                        key_mode,
+                        souffle,
+                    }),
+                    i + 1,
+                )))
+            }
+            _ => {
+                return Ok(Err(FailureExplanation {
+                    lex_pos: i,
+                    if_it_was: "directive".into(),
+                    failed_because: format!("pattern missmatch on {:#?} state", state),
+                    parent_failure: vec![],
+                }))
+            }
+        }
+    }
+
+    Ok(Err(FailureExplanation {
+        lex_pos: lexograms.len() - 1,
+        if_it_was: "directive".into(),
+        failed_because: "file ended".into(),
+        parent_failure: vec![],
+    }))
+}
@@ -1,4 +1,8 @@
-use crate::{lexer::Lexogram, utils::print_hilighted};
+use crate::{
+    error::{DlError, SyntaxFailure},
+    lexer::Lexogram,
+    utils::source_span,
+};
 
 #[derive(Debug)]
 
@@ -10,40 +14,18 @@ pub struct FailureExplanation {
 }
 
 impl FailureExplanation {
-    pub fn print(
-        self,
-        lex_list: &Vec<Lexogram>,
-        original_string: &String,
-        indentation: String,
-    ) -> String {
-        let mut ret = format!(
-            "{indentation}Error trying to read a \x1b[1m{}\x1b[0m failed because:\n",
-            self.if_it_was,
-        );
+    pub fn into_syntax_failure(self, lex_list: &Vec<Lexogram>) -> SyntaxFailure {
         let error_lex = &lex_list[self.lex_pos];
-
-        if !self.parent_failure.is_empty() {
-            for parent in self.parent_failure {
-                ret += &parent.print(
-                    lex_list,
-                    original_string,
-                    indentation.to_owned() + "\x1b[90m| \x1b[0m".into(),
-                );
-            }
-        } else {
-            ret += &format!(
-                "{indentation}\x1b[1m{}\x1b[0m starting at:\n{indentation}{}\n{indentation}\n",
-                self.failed_because,
-                print_hilighted(
-                    original_string,
-                    error_lex.pos_s,
-                    error_lex.pos_f,
-                    indentation.clone(),
-                )
-            );
-        };
-
-        ret
+        SyntaxFailure {
+            if_it_was: self.if_it_was,
+            failed_because: self.failed_because,
+            span: Some(source_span(error_lex.pos_s, error_lex.pos_f)),
+            causes: self
+                .parent_failure
+                .into_iter()
+                .map(|parent| parent.into_syntax_failure(lex_list))
+                .collect(),
+        }
     }
 }
 
@@ -59,10 +41,15 @@ impl From<String> for ParserError {
     }
 }
 impl ParserError {
-    pub fn print(self, lexic: &Vec<Lexogram>, commands: &String) -> String {
+    pub fn into_dl_error(self, lexic: &Vec<Lexogram>) -> DlError {
         match self {
-            ParserError::Custom(str) => format!("custom error on parsing: {str}"),
-            ParserError::SyntaxError(e) => e.print(lexic, commands, "".into()),
+            ParserError::Custom(str) => DlError::Syntax(SyntaxFailure {
+                if_it_was: "line".into(),
+                failed_because: format!("custom error on parsing: {str}"),
+                span: None,
+                causes: vec![],
+            }),
+            ParserError::SyntaxError(e) => DlError::Syntax(e.into_syntax_failure(lexic)),
         }
     }
 }
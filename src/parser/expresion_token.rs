@@ -1,3 +1,4 @@
+use std::collections::BTreeSet;
 use std::fmt::{self};
 
 use print_macros::*;
@@ -34,6 +35,8 @@ impl fmt::Display for VarName {
                 ret += &"]";
                 write!(f, "{ret}")
             }
+            // names of anonymous vars aren't valid identifiers, they are read back as `_`
+            VarName::Direct(name) if name.starts_with('_') => write!(f, "_"),
             VarName::Direct(name) => write!(f, "{name}"),
             VarName::ExplodeArray(name) => write!(f, "...{name}"),
         }
@@ -59,14 +62,46 @@ pub enum Expresion {
 impl fmt::Display for Expresion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Expresion::Arithmetic(expa, expb, op) => write!(f, "{expa}{}{expb}", op.to_string),
-            Expresion::Literal(l) => write!(f, "{l}"),
+            // operations are read from left to right, so only the right operand needs parens
+            Expresion::Arithmetic(expa, expb, op) => match expb.as_ref() {
+                Expresion::Arithmetic(_, _, _) => write!(f, "{expa}{}({expb})", op.to_string),
+                Expresion::Literal(Data::Number(n)) if n.is_sign_negative() => {
+                    write!(f, "{expa}{}({expb})", op.to_string)
+                }
+                _ => write!(f, "{expa}{}{expb}", op.to_string),
+            },
+            Expresion::Literal(l) => write!(f, "{}", l.to_source()),
             Expresion::Var(v) => write!(f, "{v}"),
         }
     }
 }
 
 impl Expresion {
+    pub fn is_generative(&self) -> bool {
+        match self {
+            Expresion::Arithmetic(_, _, _) => true,
+            Expresion::Var(VarName::DestructuredArray(_)) => true,
+            Expresion::Literal(_) | Expresion::Var(_) => false,
+        }
+    }
+
+    pub fn get_vars(&self) -> BTreeSet<String> {
+        match self {
+            Expresion::Arithmetic(exp_a, exp_b, _) => {
+                let mut ret = exp_a.get_vars();
+                ret.extend(exp_b.get_vars());
+                ret
+            }
+            Expresion::Literal(_) => BTreeSet::new(),
+            Expresion::Var(VarName::Direct(name) | VarName::ExplodeArray(name)) => {
+                BTreeSet::from([name.to_owned()])
+            }
+            Expresion::Var(VarName::DestructuredArray(exps)) => {
+                exps.iter().flat_map(|exp| exp.get_vars()).collect()
+            }
+        }
+    }
+
     pub fn literalize(self: &Expresion, context: &VarContext) -> Result<Data, String> {
         let ret = match self.to_owned() {
             Expresion::Arithmetic(a, b, f) => {
@@ -273,7 +308,7 @@ pub fn read_expresion(
                 state = SpectingItemOrOpenParenthesis;
             }
             (LeftParenthesis, SpectingItemOrOpenParenthesis, _) => {
-                match read_expresion(lexograms, i, only_literals)? {
+                match read_expresion(lexograms, i + 1, only_literals)? {
                     Ok((e, jump_to)) => {
                         cursor = jump_to;
                         op_ret = Some(match (&append_mode, op_ret) {
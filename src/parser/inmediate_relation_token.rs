@@ -27,7 +27,7 @@ impl fmt::Display for InmediateRelation {
 
         args += &"(";
         for (i, d) in self.args.iter().enumerate() {
-            args += &format!("{d}");
+            args += &d.to_source();
             if i != self.args.len() - 1 {
                 args += &",";
             }
@@ -1,16 +1,24 @@
 use core::fmt;
+use std::collections::BTreeMap;
 
 use super::{
     assumption_token::{read_assumption, Assumption},
+    constraint_token::{read_constraint, Constraint},
     defered_relation_token::{read_defered_relation, DeferedRelation},
+    directive_token::{read_directive, Declaration, Directive},
     error::*,
+    transaction_token::{read_transaction_control, TransactionControl},
 };
+use crate::engine::RelId;
 use crate::lexer::{self, LexogramType};
 
 #[derive(Debug, Clone)]
 pub enum Line {
     Assumption(Assumption),
     Query(DeferedRelation),
+    Directive(Directive),
+    Transaction(TransactionControl),
+    Constraint(Constraint),
     Comment(Box<Line>),
 }
 
@@ -19,15 +27,57 @@ impl fmt::Display for Line {
         match self {
             Line::Assumption(ass) => write!(f, "{ass}"),
             Line::Query(que) => write!(f, "{que}"),
+            Line::Directive(directive) => write!(f, "{directive}"),
+            Line::Transaction(control) => write!(f, "{control}"),
+            Line::Constraint(constraint) => write!(f, "{constraint}"),
             Line::Comment(line) => write!(f, "#{line}"),
         }
     }
 }
 
+impl Line {
+    // named arguments of every relation in the line placed by their column
+    pub fn place_named_args(
+        &mut self,
+        declarations: &BTreeMap<RelId, Declaration>,
+    ) -> Result<(), String> {
+        match self {
+            Line::Assumption(assumption) => assumption.place_named_args(declarations),
+            Line::Query(query) => query.place_named_args(declarations),
+            Line::Constraint(constraint) => constraint.condition.place_named_args(declarations),
+            Line::Directive(_) | Line::Transaction(_) | Line::Comment(_) => Ok(()),
+        }
+    }
+}
+
 pub fn read_line(
     lexograms: &Vec<lexer::Lexogram>,
     start_cursor: usize,
 ) -> Result<Result<(Line, usize), FailureExplanation>, ParserError> {
+    // souffle directives are the only lines starting with a dot, and declarations the only
+    // ones starting with decl and a name
+    if let LexogramType::Identifier(name) = &lexograms[start_cursor].l_type {
+        let declares = name == "decl"
+            && matches!(
+                lexograms.get(start_cursor + 1).map(|lex| &lex.l_type),
+                Some(LexogramType::Identifier(_))
+            );
+        if name.starts_with('.') || declares {
+            return Ok(read_directive(lexograms, start_cursor)?
+                .map(|(directive, jump_to)| (Line::Directive(directive), jump_to)));
+        }
+    }
+
+    // constraints are the only lines starting with :-
+    if let LexogramType::TrueWhen | LexogramType::Denial = lexograms[start_cursor].l_type {
+        return Ok(read_constraint(lexograms, start_cursor)?
+            .map(|(constraint, jump_to)| (Line::Constraint(constraint), jump_to)));
+    }
+
+    if let Ok((control, jump_to)) = read_transaction_control(lexograms, start_cursor)? {
+        return Ok(Ok((Line::Transaction(control), jump_to)));
+    }
+
     if let LexogramType::Comment = lexograms[start_cursor].l_type {
         match read_line(lexograms, start_cursor + 1)? {
             Ok((line, jump_to)) => return Ok(Ok((Line::Comment(Box::new(line)), jump_to))),
@@ -77,3 +77,76 @@ pub fn read_list(
         parent_failure: vec![],
     }));
 }
+
+// arguments named by their column
+pub type NamedArgs = Vec<(String, Expresion)>;
+
+// `(name: a, age: b)`
+pub fn read_named_list(
+    lexograms: &Vec<lexer::Lexogram>,
+    start_cursor: usize,
+) -> Result<Result<(NamedArgs, usize), FailureExplanation>, ParserError> {
+    #[derive(Debug, Clone, Copy)]
+    enum NamedListParserStates {
+        SpectingOpenParenthesis,
+        SpectingColumnName,
+        SpectingColon,
+        SpectingItem,
+        SpectingComaOrClosingParenthesis,
+    }
+    use NamedListParserStates::*;
+
+    printparse!("read_named_list at {}", start_cursor);
+
+    let mut cursor = start_cursor;
+    let mut ret = vec![];
+    let mut column_name = String::new();
+    let mut state = SpectingOpenParenthesis;
+
+    for (i, lex) in lexograms.iter().enumerate() {
+        if cursor > i {
+            continue;
+        }
+        match (lex.l_type.to_owned(), state) {
+            (LeftParenthesis, SpectingOpenParenthesis) => state = SpectingColumnName,
+            (Identifier(name), SpectingColumnName) => {
+                column_name = name;
+                state = SpectingColon
+            }
+            (CharColon, SpectingColon) => state = SpectingItem,
+            (_, SpectingItem) => {
+                match read_expresion(lexograms, i, false)? {
+                    Err(e) => {
+                        return Ok(Err(FailureExplanation {
+                            lex_pos: i,
+                            if_it_was: "named list".into(),
+                            failed_because: "Specting item".into(),
+                            parent_failure: (vec![e]),
+                        }))
+                    }
+                    Ok((e, i)) => {
+                        ret.push((column_name.to_owned(), e));
+                        cursor = i;
+                    }
+                }
+                state = SpectingComaOrClosingParenthesis;
+            }
+            (Coma, SpectingComaOrClosingParenthesis) => state = SpectingColumnName,
+            (RightParenthesis, SpectingComaOrClosingParenthesis) => return Ok(Ok((ret, i + 1))),
+            _ => {
+                return Ok(Err(FailureExplanation {
+                    lex_pos: i,
+                    if_it_was: "named list".into(),
+                    failed_because: format!("pattern missmatch on {:#?} state", state),
+                    parent_failure: vec![],
+                }));
+            }
+        }
+    }
+    Ok(Err(FailureExplanation {
+        lex_pos: lexograms.len() - 1,
+        if_it_was: "named list".into(),
+        failed_because: "file ended".into(),
+        parent_failure: vec![],
+    }))
+}
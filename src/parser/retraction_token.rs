@@ -0,0 +1,131 @@
+use core::fmt;
+
+use print_macros::*;
+
+use crate::lexer::LexogramType::*;
+use crate::{
+    lexer,
+    parser::{defered_relation_token::read_defered_relation, error::FailureExplanation},
+};
+
+use super::defered_relation_token::DeferedRelation;
+use super::error::ParserError;
+use super::statement_token::{read_statement, Statement};
+
+// `!rel(2,_)` or `!user(id,_) :- banned(id)`, removes every stored truth that matches the
+// filter and holds the guard
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Retraction {
+    pub filter: DeferedRelation,
+    pub guard: Option<Box<Statement>>,
+}
+
+impl fmt::Display for Retraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.guard {
+            Some(guard) => write!(f, "!{} :- {guard}", self.filter),
+            None => write!(f, "!{}", self.filter),
+        }
+    }
+}
+
+pub fn read_retraction(
+    lexograms: &Vec<lexer::Lexogram>,
+    start_cursor: usize,
+) -> Result<Result<(Retraction, usize), FailureExplanation>, ParserError> {
+    #[derive(Debug, Clone, Copy)]
+    enum RetractionParserStates {
+        SpectingNegatedRelation,
+        SpectingTrueWhenOrEnd,
+        SpectingGuard,
+    }
+    use RetractionParserStates::*;
+
+    printparse!("read_retraction at {}", start_cursor);
+
+    let mut cursor = start_cursor;
+    let mut op_filter = None;
+    let mut state = SpectingNegatedRelation;
+
+    for (i, lex) in lexograms.iter().enumerate() {
+        if cursor > i {
+            continue;
+        }
+        match (lex.l_type.to_owned(), state) {
+            (_, SpectingNegatedRelation) => match read_defered_relation(lexograms, i, false)? {
+                Ok((filter, jump_to)) if filter.negated && filter.assumptions.is_empty() => {
+                    cursor = jump_to;
+                    op_filter = Some(DeferedRelation {
+                        negated: false,
+                        ..filter
+                    });
+                    state = SpectingTrueWhenOrEnd;
+                }
+                Ok(_) => {
+                    return Ok(Err(FailureExplanation {
+                        lex_pos: i,
+                        if_it_was: "retraction".into(),
+                        failed_because: "specting a negated relation without assumptions".into(),
+                        parent_failure: vec![],
+                    }))
+                }
+                Err(e) => {
+                    return Ok(Err(FailureExplanation {
+                        lex_pos: i,
+                        if_it_was: "retraction".into(),
+                        failed_because: "specting relation".into(),
+                        parent_failure: vec![e],
+                    }))
+                }
+            },
+            (TrueWhen, SpectingTrueWhenOrEnd) => state = SpectingGuard,
+            (_, SpectingTrueWhenOrEnd) => {
+                return Ok(Ok((
+                    Retraction {
+                        filter: op_filter.unwrap_or_else(|| unreachable!()),
+                        guard: None,
+                    },
+                    i,
+                )))
+            }
+            (_, SpectingGuard) => match (read_statement(lexograms, i)?, op_filter) {
+                (Ok((mut guard, jump_to)), Some(filter)) => {
+                    guard.group_aggregates(
+                        &filter.args.iter().flat_map(|exp| exp.get_vars()).collect(),
+                    );
+                    return Ok(Ok((
+                        Retraction {
+                            filter,
+                            guard: Some(Box::new(guard)),
+                        },
+                        jump_to,
+                    )));
+                }
+                (Err(e), _) => {
+                    return Ok(Err(FailureExplanation {
+                        lex_pos: i,
+                        if_it_was: "retraction".into(),
+                        failed_because: "specting guard statement".into(),
+                        parent_failure: vec![e],
+                    }))
+                }
+                _ => unreachable!(),
+            },
+        }
+    }
+    match (state, op_filter) {
+        (SpectingTrueWhenOrEnd, Some(filter)) => Ok(Ok((
+            Retraction {
+                filter,
+                guard: None,
+            },
+            lexograms.len(),
+        ))),
+        _ => Ok(Err(FailureExplanation {
+            lex_pos: lexograms.len() - 1,
+            if_it_was: "retraction".into(),
+            failed_because: "file ended".into(),
+            parent_failure: vec![],
+        })),
+    }
+}
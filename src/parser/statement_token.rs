@@ -1,14 +1,16 @@
 use std::collections::hash_map::DefaultHasher;
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
 use std::hash::{Hash, Hasher};
 use std::{fmt, vec};
 
 use print_macros::*;
 
+use crate::engine::answer_table::AnswerTable;
+use crate::engine::join_plan::{Conjunct, ConjunctKind, JoinPlan};
 use crate::engine::recursion_tally::RecursionTally;
 use crate::engine::var_context::VarContext;
 use crate::engine::var_context_universe::VarContextUniverse;
-use crate::engine::Engine;
+use crate::engine::{Engine, RelId, RuntimeError};
 use crate::lexer::LexogramType::*;
 
 use crate::parser::defered_relation_token::read_defered_relation;
@@ -16,10 +18,13 @@ use crate::parser::expresion_token::read_expresion;
 
 use crate::lexer::{self};
 
+use super::aggregate_token::{read_aggregate, Aggregate};
 use super::data_token::Data;
 use super::defered_relation_token::DeferedRelation;
+use super::directive_token::Declaration;
 use super::error::{FailureExplanation, ParserError};
-use super::expresion_token::Expresion;
+use super::expresion_token::{Expresion, VarName};
+use super::HasRelId;
 
 #[derive(Clone, Copy)]
 enum AppendModes {
@@ -46,19 +51,26 @@ pub enum StatementSemantics {
     Not(Box<Statement>),
     ExpresionComparison(Expresion, Expresion, Comparison),
     Relation(DeferedRelation),
+    Aggregate(Expresion, Aggregate),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Statement {
-    memoizer: BTreeMap<u64, Result<VarContextUniverse, String>>,
+    memoizer: BTreeMap<u64, Result<VarContextUniverse, RuntimeError>>,
+    // the plans of a chain of `&&` by the variables bound when it's read, like the memoized
+    // contexts they last as long as the statement is read by one query
+    join_plans: BTreeMap<BTreeSet<String>, JoinPlan>,
     semantics: StatementSemantics,
+    literal: usize,
 }
 
 impl From<StatementSemantics> for Statement {
     fn from(value: StatementSemantics) -> Self {
         Self {
             memoizer: BTreeMap::new(),
+            join_plans: BTreeMap::new(),
             semantics: value,
+            literal: 0,
         }
     }
 }
@@ -68,7 +80,18 @@ impl fmt::Display for Statement {
         match &self.semantics {
             StatementSemantics::And(sta, stb) => write!(f, "({sta} && {stb})"),
             StatementSemantics::Or(sta, stb) => write!(f, "({sta} || {stb})"),
-            StatementSemantics::Not(st) => write!(f, "!({st})"),
+            // the way it's read after an operator
+            StatementSemantics::Not(st) => match &st.semantics {
+                StatementSemantics::Relation(rel) => write!(
+                    f,
+                    "{}",
+                    DeferedRelation {
+                        negated: !rel.negated,
+                        ..rel.to_owned()
+                    }
+                ),
+                _ => write!(f, "!({st})"),
+            },
             StatementSemantics::ExpresionComparison(sta, stb, Comparison::Eq) => {
                 write!(f, "({sta}={stb})")
             }
@@ -85,6 +108,7 @@ impl fmt::Display for Statement {
                 write!(f, "({sta}<={stb})")
             }
             StatementSemantics::Relation(rel) => write!(f, "{rel}"),
+            StatementSemantics::Aggregate(exp, aggregate) => write!(f, "({exp}={aggregate})"),
             StatementSemantics::True => write!(f, "true"),
         }
     }
@@ -259,6 +283,16 @@ pub fn read_statement_item(
                 let err1;
 
                 match read_defered_relation(lexograms, i, false)? {
+                    Ok((def_rel, jump_to)) if def_rel.negated => {
+                        let relation = StatementSemantics::Relation(DeferedRelation {
+                            negated: false,
+                            ..def_rel
+                        });
+                        return Ok(Ok((
+                            StatementSemantics::Not(Box::new(relation.into())).into(),
+                            jump_to,
+                        )));
+                    }
                     Ok((def_rel, jump_to)) => {
                         return Ok(Ok((StatementSemantics::Relation(def_rel).into(), jump_to)))
                     }
@@ -292,6 +326,15 @@ pub fn read_statement_item(
                 state = SpectingSecondExpresion;
             }
             (_, SpectingSecondExpresion, Some(first_expresion), Some(append_mode)) => {
+                if let OpEq = append_mode {
+                    if let Ok((aggregate, jump_to)) = read_aggregate(lexograms, i)? {
+                        return Ok(Ok((
+                            StatementSemantics::Aggregate(first_expresion, aggregate).into(),
+                            jump_to,
+                        )));
+                    }
+                }
+
                 match read_expresion(lexograms, i, false)? {
                     Ok((second_expresion, jump_to)) => {
                         return Ok(Ok((
@@ -370,7 +413,8 @@ fn merge_statements(
     negate_next_statement: &bool,
 ) -> Option<Statement> {
     Some(match (op_ret, append_mode, negate_next_statement) {
-        (None, _, _) => new_statement,
+        (None, _, false) => new_statement,
+        (None, _, true) => StatementSemantics::Not(Box::new(new_statement)).into(),
         (Some(prev_statement), AppendModes::And, false) => {
             StatementSemantics::And(Box::new(prev_statement), Box::new(new_statement)).into()
         }
@@ -392,21 +436,379 @@ fn merge_statements(
 }
 
 impl Statement {
+    // gives each relation of the statement its position, so the answer table can tell
+    // which literal is reading
+    pub fn number_literals(&mut self, next: &mut usize) {
+        match &mut self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                statement_a.number_literals(next);
+                statement_b.number_literals(next);
+            }
+            StatementSemantics::Not(statement) => statement.number_literals(next),
+            StatementSemantics::Aggregate(_, aggregate) => {
+                aggregate.statement.number_literals(next)
+            }
+            StatementSemantics::Relation(_) => {
+                self.literal = *next;
+                *next += 1;
+            }
+            StatementSemantics::True | StatementSemantics::ExpresionComparison(_, _, _) => (),
+        }
+    }
+
+    // a monotonic statement never stops holding when the relations it reads grow
+    pub fn is_monotonic(&self) -> bool {
+        match &self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                statement_a.is_monotonic() && statement_b.is_monotonic()
+            }
+            StatementSemantics::Not(_) | StatementSemantics::Aggregate(_, _) => false,
+            StatementSemantics::Relation(rel) => !rel.negated,
+            StatementSemantics::True | StatementSemantics::ExpresionComparison(_, _, _) => true,
+        }
+    }
+
+    pub fn is_generative(&self) -> bool {
+        match &self.semantics {
+            StatementSemantics::True => false,
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                statement_a.is_generative() || statement_b.is_generative()
+            }
+            StatementSemantics::Not(statement) => statement.is_generative(),
+            StatementSemantics::ExpresionComparison(exp_a, exp_b, _) => {
+                exp_a.is_generative() || exp_b.is_generative()
+            }
+            StatementSemantics::Relation(rel) => rel.args.iter().any(|exp| exp.is_generative()),
+            StatementSemantics::Aggregate(exp, aggregate) => {
+                exp.is_generative() || aggregate.statement.is_generative()
+            }
+        }
+    }
+
+    pub fn place_named_args(
+        &mut self,
+        declarations: &BTreeMap<RelId, Declaration>,
+    ) -> Result<(), String> {
+        match &mut self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                statement_a.place_named_args(declarations)?;
+                statement_b.place_named_args(declarations)
+            }
+            StatementSemantics::Not(statement) => statement.place_named_args(declarations),
+            StatementSemantics::Relation(rel) => rel.place_named_args(declarations),
+            StatementSemantics::Aggregate(_, aggregate) => {
+                aggregate.statement.place_named_args(declarations)
+            }
+            StatementSemantics::True | StatementSemantics::ExpresionComparison(_, _, _) => Ok(()),
+        }
+    }
+
+    pub fn get_vars(&self) -> BTreeSet<String> {
+        match &self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                let mut ret = statement_a.get_vars();
+                ret.extend(statement_b.get_vars());
+                ret
+            }
+            StatementSemantics::Not(statement) => statement.get_vars(),
+            StatementSemantics::ExpresionComparison(exp_a, exp_b, _) => {
+                let mut ret = exp_a.get_vars();
+                ret.extend(exp_b.get_vars());
+                ret
+            }
+            StatementSemantics::Relation(rel) => {
+                rel.args.iter().flat_map(|exp| exp.get_vars()).collect()
+            }
+            StatementSemantics::Aggregate(exp, aggregate) => {
+                let mut ret = exp.get_vars();
+                ret.extend(aggregate.statement.get_vars());
+                ret
+            }
+            StatementSemantics::True => BTreeSet::new(),
+        }
+    }
+
+    // aggregates are grouped by the variables they share with the rest of the rule
+    pub fn group_aggregates(&mut self, outer_vars: &BTreeSet<String>) {
+        match &mut self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                let mut outer_vars_a = outer_vars.to_owned();
+                outer_vars_a.extend(statement_b.get_vars());
+                let mut outer_vars_b = outer_vars.to_owned();
+                outer_vars_b.extend(statement_a.get_vars());
+
+                statement_a.group_aggregates(&outer_vars_a);
+                statement_b.group_aggregates(&outer_vars_b);
+            }
+            StatementSemantics::Not(statement) => statement.group_aggregates(outer_vars),
+            StatementSemantics::Aggregate(_, aggregate) => {
+                aggregate.group_vars = aggregate
+                    .statement
+                    .get_vars()
+                    .intersection(outer_vars)
+                    .cloned()
+                    .collect();
+
+                let mut inner_outer_vars = outer_vars.to_owned();
+                if let Some(target) = &aggregate.target {
+                    inner_outer_vars.extend(target.get_vars());
+                }
+                aggregate.statement.group_aggregates(&inner_outer_vars);
+            }
+            StatementSemantics::True
+            | StatementSemantics::ExpresionComparison(_, _, _)
+            | StatementSemantics::Relation(_) => (),
+        }
+    }
+
+    // the `_` of an aggregated statement are different unnamed variables, so every truth
+    // they match is a different solution
+    pub fn name_anonymous_vars(&mut self, next: &mut usize) {
+        match &mut self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                statement_a.name_anonymous_vars(next);
+                statement_b.name_anonymous_vars(next);
+            }
+            StatementSemantics::Not(statement) => statement.name_anonymous_vars(next),
+            StatementSemantics::Relation(rel) => {
+                for exp in rel.args.iter_mut() {
+                    if let Expresion::Literal(Data::Any) = exp {
+                        *exp = Expresion::Var(VarName::Direct(format!("_{next}")));
+                        *next += 1;
+                    }
+                }
+            }
+            StatementSemantics::True
+            | StatementSemantics::ExpresionComparison(_, _, _)
+            | StatementSemantics::Aggregate(_, _) => (),
+        }
+    }
+
+    // variables that get a value from a relation whenever the statement holds
+    pub fn get_bound_vars(&self) -> BTreeSet<String> {
+        match &self.semantics {
+            StatementSemantics::And(statement_a, statement_b) => statement_a
+                .get_bound_vars()
+                .union(&statement_b.get_bound_vars())
+                .cloned()
+                .collect(),
+            StatementSemantics::Or(statement_a, statement_b) => statement_a
+                .get_bound_vars()
+                .intersection(&statement_b.get_bound_vars())
+                .cloned()
+                .collect(),
+            StatementSemantics::Relation(rel) if !rel.negated => rel
+                .args
+                .iter()
+                .filter_map(|exp| match exp {
+                    Expresion::Var(VarName::Direct(name)) => Some(name.to_owned()),
+                    _ => None,
+                })
+                .collect(),
+            StatementSemantics::Aggregate(exp, aggregate) => {
+                let mut ret = aggregate.group_vars.to_owned();
+                if let Expresion::Var(VarName::Direct(name)) = exp {
+                    ret.insert(name.to_owned());
+                }
+                ret
+            }
+            _ => BTreeSet::new(),
+        }
+    }
+
+    pub fn get_dependencies(&self) -> Vec<RelId> {
+        match &self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                let mut ret = statement_a.get_dependencies();
+                ret.extend(statement_b.get_dependencies());
+                ret
+            }
+            StatementSemantics::Not(statement) => statement.get_dependencies(),
+            StatementSemantics::Aggregate(_, aggregate) => aggregate.statement.get_dependencies(),
+            StatementSemantics::Relation(rel) => vec![rel.get_rel_id()],
+            StatementSemantics::True | StatementSemantics::ExpresionComparison(_, _, _) => vec![],
+        }
+    }
+
+    // relations read under a negation or an aggregate, they have to be complete before
+    // being read
+    pub fn get_stratified_dependencies(&self) -> Vec<RelId> {
+        match &self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                let mut ret = statement_a.get_stratified_dependencies();
+                ret.extend(statement_b.get_stratified_dependencies());
+                ret
+            }
+            StatementSemantics::Not(statement) => statement.get_dependencies(),
+            StatementSemantics::Aggregate(_, aggregate) => aggregate.statement.get_dependencies(),
+            StatementSemantics::Relation(rel) if rel.negated => vec![rel.get_rel_id()],
+            _ => vec![],
+        }
+    }
+
+    pub fn has_assumptions(&self) -> bool {
+        match &self.semantics {
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                statement_a.has_assumptions() || statement_b.has_assumptions()
+            }
+            StatementSemantics::Not(statement) => statement.has_assumptions(),
+            StatementSemantics::Aggregate(_, aggregate) => aggregate.statement.has_assumptions(),
+            StatementSemantics::Relation(rel) => !rel.assumptions.is_empty(),
+            StatementSemantics::True | StatementSemantics::ExpresionComparison(_, _, _) => false,
+        }
+    }
+
+    // the contexts of a binding independent statement only depend on the variables it is
+    // given as a filter, so it can be evaluated first and its bindings passed sideways
+    fn is_binding_independent(&self, table: &AnswerTable) -> bool {
+        match &self.semantics {
+            StatementSemantics::True => true,
+            StatementSemantics::And(statement_a, statement_b)
+            | StatementSemantics::Or(statement_a, statement_b) => {
+                statement_a.is_binding_independent(table)
+                    && statement_b.is_binding_independent(table)
+            }
+            StatementSemantics::Relation(rel) => {
+                !rel.negated && rel.assumptions.is_empty() && table.is_subsumable(&rel.get_rel_id())
+            }
+            StatementSemantics::Not(_)
+            | StatementSemantics::ExpresionComparison(_, _, _)
+            | StatementSemantics::Aggregate(_, _) => false,
+        }
+    }
+
+    // the statements joined by a chain of `&&`
+    fn get_conjuncts(&self) -> Vec<&Statement> {
+        match &self.semantics {
+            StatementSemantics::And(statement_a, statement_b) => {
+                let mut ret = statement_a.get_conjuncts();
+                ret.extend(statement_b.get_conjuncts());
+                ret
+            }
+            _ => vec![self],
+        }
+    }
+
+    fn get_conjuncts_mut(&mut self) -> Vec<&mut Statement> {
+        if !matches!(self.semantics, StatementSemantics::And(_, _)) {
+            return vec![self];
+        }
+        match &mut self.semantics {
+            StatementSemantics::And(statement_a, statement_b) => {
+                let mut ret = statement_a.get_conjuncts_mut();
+                ret.extend(statement_b.get_conjuncts_mut());
+                ret
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn get_conjunct(&self, engine: &Engine, table: &AnswerTable) -> Conjunct {
+        let kind = match &self.semantics {
+            StatementSemantics::Relation(rel) if self.is_binding_independent(table) => {
+                ConjunctKind::Relation {
+                    args: rel
+                        .args
+                        .iter()
+                        .filter(|exp| !matches!(exp, Expresion::Literal(Data::Any)))
+                        .map(|exp| exp.get_vars())
+                        .collect(),
+                    size: engine
+                        .get_table(rel.get_rel_id())
+                        .and_then(|relation| relation.get_stored_size()),
+                }
+            }
+            StatementSemantics::ExpresionComparison(exp_a, exp_b, Comparison::Eq) => {
+                ConjunctKind::Equality(exp_a.get_vars(), exp_b.get_vars())
+            }
+            _ => ConjunctKind::Dependent,
+        };
+        // a relation solves every variable of its arguments, destructured ones included
+        let binds = match &self.semantics {
+            StatementSemantics::Relation(_)
+            | StatementSemantics::ExpresionComparison(_, _, Comparison::Eq) => self.get_vars(),
+            _ => self.get_bound_vars(),
+        };
+
+        Conjunct {
+            text: self.to_string(),
+            kind,
+            vars: self.get_vars(),
+            binds,
+        }
+    }
+
+    // the order the conjuncts of the statement are read in when the given variables are bound
+    pub fn get_join_plan(
+        &self,
+        engine: &Engine,
+        table: &AnswerTable,
+        bound: &BTreeSet<String>,
+    ) -> JoinPlan {
+        let conjuncts: Vec<Conjunct> = self
+            .get_conjuncts()
+            .iter()
+            .map(|conjunct| conjunct.get_conjunct(engine, table))
+            .collect();
+        JoinPlan::new(&conjuncts, bound)
+    }
+
+    // a chain of `&&` is read in the order of its plan, each conjunct over the contexts of
+    // the ones before it. Once there are none left the rest isn't read
+    fn get_join_contexts(
+        &mut self,
+        engine: &Engine,
+        recursion_tally: &RecursionTally,
+        table: &mut AnswerTable,
+        universe: &VarContextUniverse,
+    ) -> Result<VarContextUniverse, RuntimeError> {
+        let bound = universe.get_bound_vars();
+        if !self.join_plans.contains_key(&bound) {
+            let plan = self.get_join_plan(engine, table, &bound);
+            printprocess!("join plan of {}:\n{}", self, plan);
+            self.join_plans.insert(bound.to_owned(), plan);
+        }
+        let order = self.join_plans[&bound].get_order();
+
+        let mut conjuncts = self.get_conjuncts_mut();
+        let mut ret = universe.to_owned();
+        for i in order {
+            if ret.is_empty() {
+                break;
+            }
+            ret = conjuncts[i].memo_get_posible_contexts(engine, recursion_tally, table, &ret)?;
+        }
+        Ok(ret)
+    }
+
     pub fn memo_get_posible_contexts(
         &mut self,
         engine: &Engine,
         recursion_tally: &RecursionTally,
+        table: &mut AnswerTable,
         universe: &VarContextUniverse,
-    ) -> Result<VarContextUniverse, String> {
+    ) -> Result<VarContextUniverse, RuntimeError> {
         printprocess!(
             "get posible contexts of {} over universe:{}",
             self,
             universe
         );
 
+        // statements are cloned for every read of their rule, so the engine can't change while
+        // the memoized contexts are kept
         let mut memo_hash = DefaultHasher::new();
-        engine.hash(&mut memo_hash);
         universe.hash(&mut memo_hash);
+        table.get_delta().hash(&mut memo_hash);
 
         let hash = memo_hash.finish();
 
@@ -415,7 +817,7 @@ impl Statement {
 
             recall.to_owned()?
         } else {
-            let ret = self.get_posible_contexts(engine, recursion_tally, universe);
+            let ret = self.get_posible_contexts(engine, recursion_tally, table, universe);
 
             self.memoizer.insert(hash, ret.to_owned());
 
@@ -430,54 +832,38 @@ impl Statement {
         &mut self,
         engine: &Engine,
         recursion_tally: &RecursionTally,
+        table: &mut AnswerTable,
         universe: &VarContextUniverse,
-    ) -> Result<VarContextUniverse, String> {
+    ) -> Result<VarContextUniverse, RuntimeError> {
         let ret = match &mut self.semantics {
             StatementSemantics::Or(statement_a, statement_b) => {
-                let deep_universe_a =
-                    statement_a.memo_get_posible_contexts(engine, recursion_tally, universe)?;
-
-                let deep_universe_b =
-                    statement_b.memo_get_posible_contexts(engine, recursion_tally, universe)?;
+                let deep_universe_a = statement_a.memo_get_posible_contexts(
+                    engine,
+                    recursion_tally,
+                    table,
+                    universe,
+                )?;
+
+                let deep_universe_b = statement_b.memo_get_posible_contexts(
+                    engine,
+                    recursion_tally,
+                    table,
+                    universe,
+                )?;
 
                 deep_universe_a.or(deep_universe_b)
             }
 
-            StatementSemantics::And(statement_a, statement_b) => {
-                let mut ret = universe.to_owned();
-                loop {
-                    let first_universe_a =
-                        statement_a.memo_get_posible_contexts(engine, recursion_tally, universe)?;
-
-                    let first_universe_b =
-                        statement_b.memo_get_posible_contexts(engine, recursion_tally, universe)?;
-
-                    let universe_a = statement_a.memo_get_posible_contexts(
-                        engine,
-                        recursion_tally,
-                        &first_universe_b,
-                    )?;
-
-                    let universe_b = statement_b.memo_get_posible_contexts(
-                        engine,
-                        recursion_tally,
-                        &first_universe_a,
-                    )?;
-
-                    let new_ret = universe_a.or(universe_b);
-                    if new_ret != ret {
-                        ret = new_ret
-                    } else {
-                        break;
-                    }
-                }
-                ret
+            StatementSemantics::And(_, _) => {
+                self.get_join_contexts(engine, recursion_tally, table, universe)?
             }
             StatementSemantics::Not(statement) => {
+                table.start_complete_reads();
                 let negated_contexts =
-                    statement.memo_get_posible_contexts(engine, recursion_tally, universe)?;
+                    statement.memo_get_posible_contexts(engine, recursion_tally, table, universe);
+                table.finish_complete_reads();
 
-                universe.difference(&negated_contexts) //TODO i dont think i can simplify a not, look into it
+                universe.difference(&negated_contexts?)
             }
             StatementSemantics::ExpresionComparison(exp_a, exp_b, Comparison::Eq) => {
                 printprocess!(
@@ -552,13 +938,15 @@ impl Statement {
                 );
 
                 let mut ret = VarContextUniverse::new();
+                table.enter_literal(self.literal);
 
                 for base_context in universe.iter() {
-                    let table_truths = engine.query(
-                        &rel.clone_and_apply(&base_context),
-                        &base_context,
-                        recursion_tally,
-                    )?;
+                    let filter = rel.clone_and_apply(&base_context);
+                    let table_truths = if table.reads_complete() {
+                        engine.complete_query(&filter, &base_context, recursion_tally, table)?
+                    } else {
+                        engine.tabled_query(&filter, &base_context, recursion_tally, table)?
+                    };
 
                     for truth in table_truths.into_iter() {
                         let mut unfiteable = false;
@@ -582,6 +970,62 @@ impl Statement {
                 ret
             }
 
+            StatementSemantics::Aggregate(exp, aggregate) => {
+                let mut ret = VarContextUniverse::new();
+
+                for base_context in universe.iter() {
+                    let mut base_universe = VarContextUniverse::new();
+                    base_universe.insert(base_context.to_owned());
+
+                    table.start_complete_reads();
+                    let solutions = aggregate.statement.memo_get_posible_contexts(
+                        engine,
+                        recursion_tally,
+                        table,
+                        &base_universe,
+                    );
+                    table.finish_complete_reads();
+
+                    let mut groups: BTreeMap<Vec<Data>, Vec<VarContext>> = BTreeMap::new();
+                    let group_of = |context: &VarContext| {
+                        aggregate
+                            .group_vars
+                            .iter()
+                            .map(|var| context.get(var).unwrap_or(Data::Any))
+                            .collect::<Vec<Data>>()
+                    };
+
+                    // a group given by the caller exists even without solutions
+                    let base_group = group_of(&base_context);
+                    if !base_group.iter().any(|data| matches!(data, Data::Any)) {
+                        groups.insert(base_group, vec![]);
+                    }
+                    for solution in solutions?.iter() {
+                        groups
+                            .entry(group_of(&solution))
+                            .or_default()
+                            .push(solution);
+                    }
+
+                    for (group, solutions) in groups {
+                        let Some(value) = aggregate.reduce(&solutions)? else {
+                            continue;
+                        };
+
+                        let mut context = base_context.to_owned();
+                        for (var, data) in aggregate.group_vars.iter().zip(group) {
+                            if !matches!(data, Data::Any) {
+                                context.set(var.to_owned(), data);
+                            }
+                        }
+                        if let Ok(context) = exp.solve(&value, &context) {
+                            ret.insert(context);
+                        }
+                    }
+                }
+                ret
+            }
+
             StatementSemantics::True => universe.to_owned(),
         };
 
@@ -0,0 +1,54 @@
+use std::fmt;
+
+use print_macros::*;
+
+use crate::lexer::{self, LexogramType::*};
+
+use super::error::{FailureExplanation, ParserError};
+
+// `begin` starts a batch of lines that `commit` keeps and `rollback` undoes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionControl {
+    Begin,
+    Commit,
+    Rollback,
+}
+
+impl fmt::Display for TransactionControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransactionControl::Begin => write!(f, "begin"),
+            TransactionControl::Commit => write!(f, "commit"),
+            TransactionControl::Rollback => write!(f, "rollback"),
+        }
+    }
+}
+
+// the keywords are only read when they aren't the name of a relation, like `begin(1)`
+pub fn read_transaction_control(
+    lexograms: &[lexer::Lexogram],
+    start_cursor: usize,
+) -> Result<Result<(TransactionControl, usize), FailureExplanation>, ParserError> {
+    printparse!("read_transaction_control at {}", start_cursor);
+
+    let control = match lexograms[start_cursor].l_type.to_owned() {
+        Identifier(keyword) if keyword == "begin" => Some(TransactionControl::Begin),
+        Identifier(keyword) if keyword == "commit" => Some(TransactionControl::Commit),
+        Identifier(keyword) if keyword == "rollback" => Some(TransactionControl::Rollback),
+        _ => None,
+    };
+    let followed_by_args = matches!(
+        lexograms.get(start_cursor + 1).map(|lex| &lex.l_type),
+        Some(LeftParenthesis)
+    );
+
+    match (control, followed_by_args) {
+        (Some(control), false) => Ok(Ok((control, start_cursor + 1))),
+        _ => Ok(Err(FailureExplanation {
+            lex_pos: start_cursor,
+            if_it_was: "transaction control".into(),
+            failed_because: "specting begin, commit or rollback".into(),
+            parent_failure: vec![],
+        })),
+    }
+}
@@ -0,0 +1,261 @@
+use crate::engine::LineOutcome;
+use crate::error::DlError;
+use crate::parser::data_token::Data;
+
+// how query answers are written. Table is for people, the rest are for scripts and only write
+// the answers: one json object per query, or one csv/tsv record per row with a blank line
+// between queries. Json lists the undefined rows of the well founded model apart from the true
+// ones, csv and tsv leave them out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+    Tsv,
+}
+
+impl OutputFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "table" => Some(Self::Table),
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            "tsv" => Some(Self::Tsv),
+            _ => None,
+        }
+    }
+}
+
+// text shown for the outcomes of running the source
+pub fn render_outcomes(
+    outcomes: &[Result<LineOutcome, DlError>],
+    source: &str,
+    format: OutputFormat,
+    colored: bool,
+) -> String {
+    let mut renderer = Renderer::new(format, colored);
+    let mut ret = String::new();
+    for outcome in outcomes {
+        ret += &renderer.render(outcome, source);
+    }
+    ret
+}
+
+// renders outcomes one at a time, csv and tsv records of consecutive queries are separated
+// by a blank line
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    format: OutputFormat,
+    colored: bool,
+    answered: bool,
+}
+
+impl Renderer {
+    pub fn new(format: OutputFormat, colored: bool) -> Self {
+        Self {
+            format,
+            colored,
+            answered: false,
+        }
+    }
+
+    pub fn render(&mut self, outcome: &Result<LineOutcome, DlError>, source: &str) -> String {
+        let mut ret = String::new();
+        if let (Ok(LineOutcome::Truths { .. }), OutputFormat::Csv | OutputFormat::Tsv) =
+            (outcome, self.format)
+        {
+            if self.answered {
+                ret += "\n";
+            }
+            self.answered = true;
+        }
+        ret + &render_outcome(outcome, source, self.format, self.colored)
+    }
+}
+
+pub fn render_outcome(
+    outcome: &Result<LineOutcome, DlError>,
+    source: &str,
+    format: OutputFormat,
+    colored: bool,
+) -> String {
+    match (outcome, format) {
+        (
+            Ok(LineOutcome::Truths {
+                columns,
+                rows,
+                undefined,
+                ..
+            }),
+            OutputFormat::Table,
+        ) => draw_table(columns, rows, undefined),
+        (
+            Ok(LineOutcome::Truths {
+                rel_id,
+                rows,
+                undefined,
+                ..
+            }),
+            OutputFormat::Json,
+        ) => format!(
+            "{{\"relation\":{},\"rows\":{},\"undefined\":{}}}\n",
+            json_string(&rel_id.identifier),
+            json_rows(rows),
+            json_rows(undefined)
+        ),
+        (Ok(LineOutcome::Truths { rows, .. }), OutputFormat::Csv) => {
+            draw_records(rows, ",", csv_field)
+        }
+        (Ok(LineOutcome::Truths { rows, .. }), OutputFormat::Tsv) => {
+            draw_records(rows, "\t", tsv_field)
+        }
+        (Ok(_), OutputFormat::Json | OutputFormat::Csv | OutputFormat::Tsv) => String::new(),
+        (Ok(LineOutcome::Retracted(1)), _) => "\nRemoved 1 truth\n".into(),
+        (Ok(LineOutcome::Retracted(count)), _) => format!("\nRemoved {count} truths\n"),
+        (Ok(LineOutcome::RuleRetracted), _) => "\nRemoved 1 rule\n".into(),
+        (Ok(LineOutcome::Assumed | LineOutcome::Comment), _) => String::new(),
+        (Err(err), _) => err.render(source, colored),
+    }
+}
+
+pub fn to_json(data: &Data) -> String {
+    match data {
+        Data::Number(n) if n.is_finite() => format!("{n}"),
+        Data::Number(_) | Data::Any => "null".into(),
+        Data::String(s) => json_string(s),
+        Data::Array(array) => json_row(array),
+    }
+}
+
+fn json_row(row: &[Data]) -> String {
+    format!(
+        "[{}]",
+        row.iter().map(to_json).collect::<Vec<String>>().join(",")
+    )
+}
+
+fn json_rows(rows: &[Vec<Data>]) -> String {
+    format!(
+        "[{}]",
+        rows.iter()
+            .map(|row| json_row(row))
+            .collect::<Vec<String>>()
+            .join(",")
+    )
+}
+
+fn json_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret += "\\\"",
+            '\\' => ret += "\\\\",
+            '\n' => ret += "\\n",
+            '\r' => ret += "\\r",
+            '\t' => ret += "\\t",
+            c if (c as u32) < 0x20 => ret += &format!("\\u{:04x}", c as u32),
+            c => ret.push(c),
+        }
+    }
+    ret + "\""
+}
+
+// strings are written raw and arrays as json
+fn csv_field(data: &Data) -> String {
+    let field = match data {
+        Data::String(s) => s.to_owned(),
+        Data::Any => String::new(),
+        data => to_json(data),
+    };
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+fn tsv_field(data: &Data) -> String {
+    match data {
+        Data::String(s) => s
+            .replace('\\', "\\\\")
+            .replace('\t', "\\t")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r"),
+        Data::Any => String::new(),
+        data => to_json(data),
+    }
+}
+
+fn draw_records(rows: &[Vec<Data>], separator: &str, field: fn(&Data) -> String) -> String {
+    let mut ret = String::new();
+    for row in rows {
+        ret += &row
+            .iter()
+            .map(field)
+            .collect::<Vec<String>>()
+            .join(separator);
+        ret += "\n";
+    }
+    ret
+}
+
+// the names of the columns head the table when there are any. Undefined rows are shown in
+// order with the true ones
+pub fn draw_table(columns: &[String], rows: &[Vec<Data>], undefined: &[Vec<Data>]) -> String {
+    let mut rows: Vec<(&Vec<Data>, bool)> = rows
+        .iter()
+        .map(|row| (row, false))
+        .chain(undefined.iter().map(|row| (row, true)))
+        .collect();
+    rows.sort();
+    if rows.is_empty() {
+        return "\nEmpty Result\n".into();
+    }
+    let mut ret = String::from("\n");
+    let column_count = rows[0].0.len();
+
+    let header_width = columns
+        .iter()
+        .map(|name| name.len())
+        .collect::<Vec<usize>>();
+    let col_width = rows.iter().fold(vec![0; column_count], |acc, (elm, _)| {
+        let mut ret = acc.clone();
+        elm.iter().enumerate().for_each(|(i, e)| {
+            let e_size = e.to_string().len();
+            ret[i] = ret[i].max(e_size).max(*header_width.get(i).unwrap_or(&0));
+        });
+        ret
+    });
+
+    if columns.len() == column_count {
+        ret += " ";
+        for (i, name) in columns.iter().enumerate() {
+            ret += name;
+            if i != column_count - 1 {
+                ret += &" ".repeat(col_width[i] - name.len() + 2);
+            }
+        }
+        ret += "\n";
+    }
+
+    for (row, undefined) in rows {
+        ret += "(";
+        for (i, elm) in row.iter().enumerate() {
+            let representation = elm.to_string();
+            ret += &representation;
+
+            for _ in 0..col_width[i] - representation.len() {
+                ret += " ";
+            }
+            if i != column_count - 1 {
+                ret += ", "
+            }
+        }
+        ret += ")";
+        if undefined {
+            ret += " undefined";
+        }
+        ret += "\n";
+    }
+    ret
+}
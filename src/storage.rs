@@ -0,0 +1,139 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::error::DlError;
+
+// a fact base kept in a directory. `snapshot.<n>.dl` is the exported source of the engine and
+// `log.<n>.dl` the source of every change made after it, one line each. A new snapshot starts
+// the next generation, and the old one is only removed once the new one is on disk, so a crash
+// at any point leaves a generation that can be recovered
+#[derive(Debug, Clone)]
+pub struct Storage {
+    directory: PathBuf,
+    generation: usize,
+    // lines logged since the last snapshot, a new one is taken when they reach the interval
+    logged: usize,
+    snapshot_interval: usize,
+}
+
+impl Storage {
+    // the storage of the directory, with the snapshot and the log to recover from
+    pub fn open(directory: &Path) -> Result<(Self, String, String), DlError> {
+        fs::create_dir_all(directory)?;
+
+        let mut files = vec![];
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            if let Some(file) = read_file_name(&path) {
+                files.push((path, file));
+            }
+        }
+        let generation = files
+            .iter()
+            .filter(|(_, (kind, _))| *kind == "snapshot")
+            .map(|(_, (_, generation))| *generation)
+            .max()
+            .unwrap_or(0);
+
+        // whatever a crash left from older generations or unfinished snapshots
+        for (path, (kind, file_generation)) in files.iter() {
+            if *file_generation < generation || *kind == "tmp" {
+                fs::remove_file(path)?;
+            }
+        }
+
+        let storage = Self {
+            directory: directory.to_owned(),
+            generation,
+            logged: 0,
+            snapshot_interval: 1000,
+        };
+        let snapshot = match generation {
+            0 => String::new(),
+            _ => fs::read_to_string(storage.snapshot_path(generation))?,
+        };
+        let log = match fs::read_to_string(storage.log_path(generation)) {
+            Ok(log) => log,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        // a line is only logged once it ends, anything after the last line break was cut by
+        // a crash while it was written and is dropped so new lines don't follow it
+        let complete = log.rfind('\n').map(|end| end + 1).unwrap_or(0);
+        if complete < log.len() {
+            OpenOptions::new()
+                .write(true)
+                .open(storage.log_path(generation))?
+                .set_len(complete as u64)?;
+        }
+        let log = log[..complete].to_owned();
+        Ok((storage, snapshot, log))
+    }
+
+    pub fn set_snapshot_interval(&mut self, lines: usize) {
+        self.snapshot_interval = lines;
+    }
+
+    fn snapshot_path(&self, generation: usize) -> PathBuf {
+        self.directory.join(format!("snapshot.{generation}.dl"))
+    }
+
+    fn log_path(&self, generation: usize) -> PathBuf {
+        self.directory.join(format!("log.{generation}.dl"))
+    }
+
+    // true when it's time for a snapshot
+    pub fn append(&mut self, source: &str) -> Result<bool, DlError> {
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.log_path(self.generation))?;
+        log.write_all(format!("{source}\n").as_bytes())?;
+        log.sync_data()?;
+
+        self.logged += 1;
+        Ok(self.logged >= self.snapshot_interval)
+    }
+
+    pub fn snapshot(&mut self, exported: &str) -> Result<(), DlError> {
+        let generation = self.generation + 1;
+
+        let temporary = self.directory.join(format!("snapshot.{generation}.tmp"));
+        let mut file = File::create(&temporary)?;
+        file.write_all(exported.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&temporary, self.snapshot_path(generation))?;
+        if let Ok(directory) = File::open(&self.directory) {
+            directory.sync_all().ok();
+        }
+
+        for old in [
+            self.snapshot_path(self.generation),
+            self.log_path(self.generation),
+        ] {
+            match fs::remove_file(old) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => (),
+            }
+        }
+        self.generation = generation;
+        self.logged = 0;
+        Ok(())
+    }
+}
+
+// `snapshot.<n>.dl`, `log.<n>.dl` or `snapshot.<n>.tmp` as its kind and generation
+fn read_file_name(path: &Path) -> Option<(&'static str, usize)> {
+    let name = path.file_name()?.to_str()?;
+    let (stem, extension) = name.rsplit_once('.')?;
+    let (prefix, generation) = stem.split_once('.')?;
+    let generation = generation.parse::<usize>().ok()?;
+    match (prefix, extension) {
+        ("snapshot", "dl") => Some(("snapshot", generation)),
+        ("log", "dl") => Some(("log", generation)),
+        ("snapshot", "tmp") => Some(("tmp", generation)),
+        _ => None,
+    }
+}
@@ -1,7 +1,11 @@
 #[cfg(test)]
 
 mod tests {
-    use crate::engine::Engine;
+    use crate::delimited::{ColumnType, Delimiter, ImportOptions};
+    use crate::engine::{Engine, LineOutcome, RelId, RuntimeError};
+    use crate::error::{DlError, Span};
+    use crate::parser::data_token::Data;
+    use crate::render::{render_outcomes, OutputFormat};
 
     #[test]
     fn query_full_table_1() {
@@ -57,7 +61,7 @@ mod tests {
     fn view_2() {
         let mut engine = Engine::new();
         assert_eq!(
-            "\n(3)\n(4)\n",
+            "\nRemoved 1 truth\n\n(3)\n(4)\n",
             engine.input("rel(4,4) rel(0,1) rel(2,3) rel(2,2) !rel(2,2) rel(3,3) test(a) :- rel(a,a) test(_)?".into())
         );
     }
@@ -350,4 +354,1029 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn long_chain_conectivity() {
+        let mut engine = Engine::new();
+        engine.set_recursion_limit(2);
+        let edges: String = (1..12).map(|i| format!("edge({i},{}) ", i + 1)).collect();
+        let expected: String = (1..=12).map(|i| format!("(1, {i:<2})\n")).collect();
+        assert_eq!(
+            format!("\n{expected}"),
+            engine.input(
+                edges
+                    + "conected(a,b) :- conected(a,mid) && edge(mid,b) conected(a,a) :- true conected(1,_)?"
+            )
+        );
+    }
+
+    #[test]
+    fn generation_past_recursion_limit_is_an_error() {
+        let mut engine = Engine::new();
+        engine.set_recursion_limit(3);
+        assert!(matches!(
+            engine.run("test(a+1) :- test(a) test(0) test(_)?".into()).pop(),
+            Some(Err(DlError::Runtime {
+                error: RuntimeError::RecursionLimit(RelId { ref identifier, column_count: 1 }, 3),
+                ..
+            })) if identifier == "test"
+        ));
+
+        // the rule ends on its own but it needs more rounds than the limit allows
+        let mut engine = Engine::new();
+        assert!(matches!(
+            engine
+                .run("test(0) test(a+1) :- test(a) && a < 20 test(_)?".into())
+                .pop(),
+            Some(Err(DlError::Runtime {
+                error: RuntimeError::RecursionLimit(..),
+                ..
+            }))
+        ));
+        engine.set_recursion_limit(20);
+        match engine.run("test(_)?".into()).pop() {
+            Some(Ok(LineOutcome::Truths { rows, .. })) => assert_eq!(21, rows.len()),
+            other => panic!("unexpected outcome {other:?}"),
+        }
+    }
+
+    #[test]
+    fn bound_query_on_range_restricted_recursion() {
+        let mut engine = Engine::new();
+        let unrelated_edges: String = (100..160)
+            .map(|i| format!("edge({i},{}) ", i + 1))
+            .collect();
+        assert_eq!(
+            "\n(1, 2)\n(1, 3)\n\n(1, 3)\n(2, 3)\n",
+            engine.input(
+                unrelated_edges
+                    + "edge(1,2) edge(2,3) path(a,b) :- edge(a,b) path(a,b) :- path(a,mid) && edge(mid,b) path(1,_)? path(_,3)?"
+            )
+        );
+    }
+
+    #[test]
+    fn subsumed_calls_keep_filter_dependent_rules() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(1, 1)\n(1, 2)\n(1, 3)\n\n(3, 3)\n",
+            engine.input(
+                "edge(1,2) edge(2,3) reach(a,b) :- reach(a,mid) && edge(mid,b) reach(a,a) :- true reach(1,_)? reach(3,_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn negation_of_recursive_relation() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(4)\n(5)\n\n(5)\n",
+            engine.input(
+                "n(1) n(2) n(3) n(4) n(5) edge(1,2) edge(2,3) edge(3,1) edge(4,5) reach(a,b) :- edge(a,b) reach(a,b) :- reach(a,mid) && edge(mid,b) unreached(x) :- n(x) && !reach(1,x) unreached(_)? sink(x) :- n(x) && !reach(x,_) sink(_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn leading_negation() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(1)\n(3)\n",
+            engine.input("q(1) q(2) q(3) r(2) p(x) :- !r(x) && q(x) p(_)?".into())
+        );
+    }
+
+    #[test]
+    fn negative_cycle_is_rejected() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "An error ocurred on the execution step: \n\u{1b}[1mthe program can't be stratified, a depends on its own negation or aggregate: a -> b -> a\u{1b}[0m at:\n& !b(x) b(x) :- q(x) && !a(x) \u{1b}[1m\u{1b}[37;41ma(_)?\u{1b}[0m\n",
+            engine.input(
+                "q(1) a(x) :- q(x) && !b(x) b(x) :- q(x) && !a(x) a(_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn well_founded_model_of_unstratifiable_program() {
+        let mut engine = Engine::new();
+        engine.set_well_founded(true);
+        assert_eq!(
+            "\n(1) undefined\n(2) undefined\n(3)\n",
+            engine.input(
+                "move(1,2) move(2,1) move(2,3) move(3,4) win(x) :- move(x,y) && !win(y) win(_)?"
+                    .into()
+            )
+        );
+
+        // scripts only get the true rows as rows in every format
+        let outcomes = engine.run("win(_)?".into());
+        assert_eq!(
+            "{\"relation\":\"win\",\"rows\":[[3]],\"undefined\":[[1],[2]]}\n",
+            render_outcomes(&outcomes, "win(_)?", OutputFormat::Json, false)
+        );
+        assert_eq!(
+            "3\n",
+            render_outcomes(&outcomes, "win(_)?", OutputFormat::Csv, false)
+        );
+    }
+
+    #[test]
+    fn count_grouped_by_head() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(1, 2)\n(2, 1)\n(3, 0)\n",
+            engine.input(
+                "node(1) node(2) node(3) edge(1,2) edge(1,3) edge(2,3) out(x,n) :- node(x) && n = count(edge(x,_)) out(_,_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn sum_min_and_avg() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(1, 12)\n(2, 4 )\n\n(4)\n\n(1, 6)\n(2, 4)\n",
+            engine.input(
+                "cost(1,5) cost(1,7) cost(2,4) total(x,t) :- t = sum(c : cost(x,c)) total(_,_)? cheapest(m) :- m = min(c : cost(_,c)) cheapest(_)? average(x,a) :- a = avg(c : cost(x,c)) average(_,_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn update_truths() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(1, 20)\n(2, 30)\n\n(1, 0)\n(2, 0)\n\n(1, 20)\n(2, 30)\n",
+            engine.input(
+                "balance(1,10) balance(2,20) balance(acc,x) -> balance(acc,x+10) balance(_,_)? {balance(acc,x) -> balance(acc,0)} => balance(_,_)? balance(_,_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn update_moves_truths_between_relations() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(1)\n(2)\n\nEmpty Result\n",
+            engine.input("pending(1) pending(2) pending(x) -> done(x) done(_)? pending(_)?".into())
+        );
+    }
+
+    #[test]
+    fn retract_by_pattern() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\nRemoved 2 truths\n\n(1, 1)\n(3, 3)\n\nRemoved 2 truths\n\nEmpty Result\n",
+            engine.input(
+                "rel(1,1) rel(2,1) rel(2,2) rel(3,3) !rel(2,_) rel(_,_)? !rel(x,x) rel(_,_)?"
+                    .into()
+            )
+        );
+    }
+
+    #[test]
+    fn guarded_retraction() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\nRemoved 2 truths\n\n(1, \"a\")\n",
+            engine.input(
+                "user(1,\"a\") user(2,\"b\") user(3,\"c\") banned(2) banned(3) !user(id,_) :- banned(id) user(_,_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn retract_rule() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\nRemoved 1 rule\n\n(1, 2)\n(2, 3)\n",
+            engine.input(
+                "edge(1,2) edge(2,3) path(x,y) :- edge(x,y) path(x,z) :- edge(x,y) && path(y,z) !path(x,z) :- edge(x,y) && path(y,z) path(_,_)?".into()
+            )
+        );
+        assert!(engine
+            .input("path(x,y) :- edge(x,y)".into())
+            .contains("ya existe"));
+
+        // rules reading `_` are found too
+        engine.input("user(1,\"a\") e(x) :- user(x,_)".into());
+        assert!(engine
+            .input("e(x) :- user(x,_)".into())
+            .contains("ya existe"));
+        assert_eq!(
+            "\nRemoved 1 rule\n",
+            engine.input("!e(x) :- user(x,_)".into())
+        );
+        assert_eq!("\nEmpty Result\n", engine.input("e(_)?".into()));
+
+        engine.input("e(x) :- user(x,_)".into());
+        assert!(engine.run("!e(x) :- user(_,x)".into())[0]
+            .as_ref()
+            .is_err_and(|err| err.to_string().contains("doesn't exist")));
+
+        // rules are the same up to the names of their variables, and a rule that isn't there
+        // doesn't fall back to retracting the truths it would guard
+        let mut engine = Engine::new();
+        engine.input("edge(1,2) path(1,2) path(x,y) :- edge(x,y)".into());
+        assert!(engine
+            .input("path(a,b) :- edge(a,b)".into())
+            .contains("ya existe"));
+        assert!(engine.run("!path(a,b) :- edge(b,a)".into())[0]
+            .as_ref()
+            .is_err_and(|err| err.to_string().contains("has rules")));
+        assert_eq!(
+            "\nRemoved 1 rule\n",
+            engine.input("!path(a,b) :- edge(a,b)".into())
+        );
+        assert_eq!("\n(1, 2)\n", engine.input("path(_,_)?".into()));
+    }
+
+    #[test]
+    fn rules_from_the_api() {
+        let mut engine = Engine::new();
+        engine.input("edge(1,2) edge(2,3)".into());
+        engine
+            .add_rule("path(x,y) :- edge(x,y) path(x,z) :- edge(x,y) && path(y,z)")
+            .unwrap();
+        assert_eq!(
+            "\n(1, 2)\n(1, 3)\n(2, 3)\n",
+            engine.input("path(_,_)?".into())
+        );
+
+        let err = engine
+            .add_rule("path(x,y) :- edge(y,x) edge(5,6)")
+            .unwrap_err();
+        assert!(matches!(err, DlError::Runtime { span: Some(_), .. }));
+        assert!(err.to_string().contains("isn't a rule"));
+        assert!(engine.add_rule("path(a,b) :- edge(a,b)").is_err());
+
+        engine
+            .retract_rule("path(a,c) :- edge(a,b) && path(b,c)")
+            .unwrap();
+        assert_eq!("\n(1, 2)\n(2, 3)\n", engine.input("path(_,_)?".into()));
+        assert!(engine.retract_rule("path(a,b) :- edge(b,a)").is_err());
+
+        let path = RelId {
+            identifier: "path".into(),
+            column_count: 2,
+        };
+        engine
+            .redefine_rules(&path, "path(x,y) :- edge(y,x)")
+            .unwrap();
+        assert_eq!("\n(2, 1)\n(3, 2)\n", engine.input("path(_,_)?".into()));
+    }
+
+    #[test]
+    fn redefine_rules() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(2, 1)\n(3, 2)\n\n(1, 2)\n(2, 1)\n(2, 3)\n(3, 2)\n",
+            engine.input(
+                "edge(1,2) edge(2,3) path(x,y) :- edge(x,y) path(x,z) :- edge(x,y) && path(y,z) path(x,y) := edge(y,x) path(_,_)? path(x,y) :- edge(x,y) path(_,_)?".into()
+            )
+        );
+    }
+
+    #[test]
+    fn unknown_relation() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "An error ocurred on the execution step: \n\u{1b}[1mthe relation foo/1 doesn't exist, did you mean foo/2?\u{1b}[0m at:\n\n 1 : foo(1,2) \u{1b}[1m\u{1b}[37;41mfoo(_)?\u{1b}[0m\n",
+            engine.input("foo(1,2) foo(_)?".into())
+        );
+        assert_eq!(
+            "\nEmpty Result\n",
+            engine.input("bar(x) :- baz(x) bar(_)?".into())
+        );
+
+        engine.set_strict_relations(true);
+        assert_eq!(
+            "An error ocurred on the execution step: \n\u{1b}[1mthe relation baz/1 doesn't exist\u{1b}[0m at:\n\n 1 : \u{1b}[1m\u{1b}[37;41mbar(_)?\u{1b}[0m\n",
+            engine.input("bar(_)?".into())
+        );
+        // read in a rule body it's the same error as in a query
+        assert!(matches!(
+            engine.run("bar(_)?".into()).pop(),
+            Some(Err(DlError::Runtime {
+                error: RuntimeError::UnknownRelation(RelId { ref identifier, column_count: 1 }, _),
+                ..
+            })) if identifier == "baz"
+        ));
+    }
+
+    #[test]
+    fn structured_outcomes() {
+        let mut engine = Engine::new();
+        let outcomes = engine
+            .run("rel(2,\"b\") rel(1,\"a\") #rel(3,\"c\") rel(_,_)? !rel(1,_) other(_)?".into());
+
+        assert!(matches!(outcomes[0], Ok(LineOutcome::Assumed)));
+        assert!(matches!(outcomes[2], Ok(LineOutcome::Comment)));
+        assert_eq!(
+            outcomes[3].as_ref().ok(),
+            Some(&LineOutcome::Truths {
+                rel_id: RelId {
+                    identifier: "rel".into(),
+                    column_count: 2
+                },
+                columns: vec![],
+                rows: vec![
+                    vec![Data::Number(1.0), Data::String("a".into())],
+                    vec![Data::Number(2.0), Data::String("b".into())],
+                ],
+                undefined: vec![],
+            })
+        );
+        assert!(matches!(outcomes[4], Ok(LineOutcome::Retracted(1))));
+        assert!(matches!(
+            outcomes[5],
+            Err(DlError::Runtime {
+                error: RuntimeError::UnknownRelation(_, _),
+                span: Some(Span { start: 54, end: 63 })
+            })
+        ));
+        assert_eq!(outcomes.len(), 6);
+    }
+
+    #[test]
+    fn uncolored_syntax_errors() {
+        let mut engine = Engine::new();
+        let source = "rel(1,2) rel(1,?";
+        let outcomes = engine.run(source.into());
+
+        let Some(Err(err @ DlError::Syntax(failure))) = outcomes.first() else {
+            panic!("specting a syntax error, got {outcomes:?}");
+        };
+        assert!(failure.span.is_some());
+        assert!(!err.render(source, false).contains('\x1b'));
+        assert!(err.render(source, true).contains('\x1b'));
+    }
+
+    #[test]
+    fn machine_readable_formats() {
+        let mut engine = Engine::new();
+        let source = "p(2,[1,\"x\"]) p(1,\"a,b\") p(_,_)? p(1,_)?";
+        let outcomes = engine.run(source.into());
+
+        assert_eq!(
+            "{\"relation\":\"p\",\"rows\":[[1,\"a,b\"],[2,[1,\"x\"]]],\"undefined\":[]}\n{\"relation\":\"p\",\"rows\":[[1,\"a,b\"]],\"undefined\":[]}\n",
+            render_outcomes(&outcomes, source, OutputFormat::Json, false)
+        );
+        assert_eq!(
+            "1,\"a,b\"\n2,\"[1,\"\"x\"\"]\"\n\n1,\"a,b\"\n",
+            render_outcomes(&outcomes, source, OutputFormat::Csv, false)
+        );
+        assert_eq!(
+            "1\ta,b\n2\t[1,\"x\"]\n\n1\ta,b\n",
+            render_outcomes(&outcomes, source, OutputFormat::Tsv, false)
+        );
+    }
+
+    #[test]
+    fn import_delimited_rows() {
+        let mut engine = Engine::new();
+        let mut options = ImportOptions::new(Delimiter::Comma);
+        options.header = true;
+        assert_eq!(
+            3,
+            engine
+                .import_delimited("person", "id,name\n1,ana\n2,\"b,ob\"\n03,7\n", &options)
+                .unwrap()
+        );
+
+        options.column_types = Some(vec![ColumnType::Number, ColumnType::String]);
+        assert_eq!(
+            1,
+            engine
+                .import_delimited("person", "id,name\n4,8\n", &options)
+                .unwrap()
+        );
+        assert_eq!(
+            1,
+            engine
+                .import_delimited("tagged", "x\\ty\t1\n", &ImportOptions::new(Delimiter::Tab))
+                .unwrap()
+        );
+
+        assert_eq!(
+            "\n(1, \"ana\" )\n(2, \"b,ob\")\n(3, 7     )\n(4, \"8\"   )\n\n(\"x\ty\", 1)\n",
+            engine.input("person(_,_)? tagged(_,_)?".into())
+        );
+
+        // only finite numbers are numbers, the rest are read as strings
+        let mut engine = Engine::new();
+        engine
+            .import_delimited(
+                "p",
+                "1,NaN\n2,inf\n3,-Infinity\n",
+                &ImportOptions::new(Delimiter::Comma),
+            )
+            .unwrap();
+        assert_eq!(
+            "p(1,\"NaN\")\np(2,\"inf\")\np(3,\"-Infinity\")\n",
+            engine.export()
+        );
+        let mut reloaded = Engine::new();
+        reloaded.input(engine.export());
+        assert_eq!(engine.export(), reloaded.export());
+        options.header = false;
+        assert!(matches!(
+            engine.import_delimited("q", "NaN,a\n", &options),
+            Err(DlError::Import { row: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn import_errors_name_the_row() {
+        let mut engine = Engine::new();
+        let mut options = ImportOptions::new(Delimiter::Comma);
+        options.column_types = Some(vec![ColumnType::Number, ColumnType::String]);
+
+        let err = engine
+            .import_delimited("person", "1,ana\n\nbob,2\n", &options)
+            .unwrap_err();
+        assert!(matches!(err, DlError::Import { row: 3, .. }));
+        assert_eq!(
+            "row 3: column 1 should be a number, found \"bob\"",
+            err.to_string()
+        );
+
+        let err = engine
+            .import_delimited("person", "1,ana\n2\n", &options)
+            .unwrap_err();
+        assert_eq!("row 2: specting 2 columns, found 1", err.to_string());
+        assert!(engine
+            .input("person(_,_)?".into())
+            .contains("the relation person/2 doesn't exist"));
+
+        // the relation has to be one a program can name
+        for rel_name in ["", "my rel", "my_rel", "12", ".x", "p(1)"] {
+            let err = engine
+                .import_delimited(rel_name, "1,ana\n", &options)
+                .unwrap_err();
+            assert!(err.to_string().contains("isn't a valid relation name"));
+        }
+        assert_eq!("", engine.export());
+        assert_eq!(
+            1,
+            engine
+                .import_delimited("person2", "1,ana\n", &options)
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn export_round_trips() {
+        let mut engine = Engine::new();
+        engine.input(
+            "person(\"ana \\\"la\\\\ana\\\"\",31) person(\"bob\",0-2) tags([0-1,\"x\"]) \
+            older(a) :- person(a,n) && n > 0-(1+2) && n < 40-(5-6) \
+            headcount(n) :- n = count(person(_,_)) \
+            guess(a) :- {person(\"cid\",3)}=>person(a,_)"
+                .into(),
+        );
+        let exported = engine.export();
+
+        let mut reloaded = Engine::new();
+        let outcomes = reloaded.run(exported.to_owned());
+        assert!(outcomes.iter().all(|outcome| outcome.is_ok()), "{exported}");
+        assert_eq!(exported, reloaded.export());
+
+        let queries = "person(_,_)? tags(_)? older(_)? headcount(_)? guess(_)?";
+        assert_eq!(engine.input(queries.into()), reloaded.input(queries.into()));
+    }
+
+    #[test]
+    fn export_relation_rows() {
+        let mut engine = Engine::new();
+        engine
+            .input("edge(1,2) edge(2,3) path(a,b) :- edge(a,b) || (edge(a,c) && path(c,b))".into());
+
+        let edge = engine.resolve_relation("edge").unwrap();
+        assert_eq!(
+            "1,2\n2,3\n",
+            engine
+                .export_relation(&edge, OutputFormat::Csv, false)
+                .unwrap()
+        );
+
+        let path = engine.resolve_relation("path/2").unwrap();
+        assert_eq!(
+            "",
+            engine
+                .export_relation(&path, OutputFormat::Csv, false)
+                .unwrap()
+        );
+        assert_eq!(
+            "{\"relation\":\"path\",\"rows\":[[1,2],[1,3],[2,3]],\"undefined\":[]}\n",
+            engine
+                .export_relation(&path, OutputFormat::Json, true)
+                .unwrap()
+        );
+
+        engine.input("edge(1) ".into());
+        assert!(engine.resolve_relation("edge").is_err());
+        assert!(engine.resolve_relation("path/3").is_err());
+    }
+
+    #[test]
+    fn souffle_directives() {
+        let directory =
+            std::env::temp_dir().join(format!("datalang_souffle_{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        std::fs::write(directory.join("edge.facts"), "1\t2\n2\t3\n").unwrap();
+        std::fs::write(directory.join("name.facts"), "1\t007\n").unwrap();
+
+        let mut engine = Engine::new();
+        engine.set_fact_directory(&directory);
+        engine.set_output_directory(&directory);
+        assert_eq!(
+            "\n id  who\n(1 , \"007\")\n",
+            engine.input(
+                ".decl edge(x:number, y:number) .input edge \
+                .decl name(id:number, who:symbol) .input name \
+                .decl path(x:number, y:number) .output path \
+                path(a,b) :- edge(a,b) || (edge(a,c) && path(c,b)) \
+                name(_,_)?"
+                    .into()
+            )
+        );
+        assert_eq!(
+            "1\t2\n1\t3\n2\t3\n",
+            std::fs::read_to_string(directory.join("path.csv")).unwrap()
+        );
+        assert!(engine
+            .export()
+            .starts_with(".decl edge(x:number, y:number)\n"));
+
+        assert!(engine
+            .input(".decl edge(x:number) .input nothing".into())
+            .contains("already declared"));
+        assert!(engine
+            .input(".input nothing".into())
+            .contains("isn't declared"));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn persistent_storage() {
+        let directory =
+            std::env::temp_dir().join(format!("datalang_storage_{}", std::process::id()));
+        std::fs::remove_dir_all(&directory).ok();
+
+        let mut engine = Engine::open(&directory).unwrap();
+        engine.input(
+            "edge(1,2) edge(2,3) path(a,b) :- edge(a,b) || (edge(a,c) && path(c,b)) \
+            !edge(2,3) {edge(7,7)}=>path(_,_)?"
+                .into(),
+        );
+        let queries = "path(_,_)? edge(_,_)?";
+        let answers = engine.input(queries.into());
+        assert_eq!("\n(1, 2)\n\n(1, 2)\n", answers);
+
+        // a crash while a line was being logged
+        let log = directory.join("log.0.dl");
+        let mut contents = std::fs::read_to_string(&log).unwrap();
+        contents += "edge(8,";
+        std::fs::write(&log, contents).unwrap();
+        let mut reopened = Engine::open(&directory).unwrap();
+        assert_eq!(answers, reopened.input(queries.into()));
+
+        reopened.set_snapshot_interval(2);
+        reopened.input("edge(3,4) edge(4,5) edge(5,6)".into());
+        std::fs::write(directory.join("snapshot.9.tmp"), "unfinished").unwrap();
+        let mut reopened = Engine::open(&directory).unwrap();
+        assert_eq!(
+            "\n(1, 2)\n(3, 4)\n(4, 5)\n(5, 6)\n",
+            reopened.input("edge(_,_)?".into())
+        );
+
+        let mut files: Vec<String> = std::fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(vec!["log.1.dl", "snapshot.1.dl"], files);
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn transactions() {
+        let mut engine = Engine::new();
+        assert_eq!(
+            "\n(3)\n(4)\n\n(1)\n",
+            engine.input(
+                "begin(1) begin a(2) rollback a(3) begin a(4) commit a(_)? begin(_)?".into()
+            )
+        );
+
+        // the failing line rolls back everything since begin
+        let outcomes =
+            engine.run("begin a(x) -> a(x+10) !a(13) b(x) :- a(x) begin a(_)? commit".into());
+        assert!(matches!(
+            outcomes.last(),
+            Some(Err(DlError::Runtime { .. }))
+        ));
+        assert_eq!("\n(3)\n(4)\n", engine.input("a(_)?".into()));
+        assert!(engine
+            .input("commit".into())
+            .contains("there's no transaction to commit"));
+
+        let mut options = ImportOptions::new(Delimiter::Comma);
+        options.column_types = Some(vec![ColumnType::Number]);
+        let imported = engine.transaction(|tx| {
+            tx.import_delimited("a", "5\n6\n", &options)?;
+            tx.import_delimited("a", "7\nseven\n", &options)
+        });
+        assert!(matches!(imported, Err(DlError::Import { row: 2, .. })));
+        assert_eq!("\n(3)\n(4)\n", engine.input("a(_)?".into()));
+
+        let imported = engine.transaction(|tx| tx.import_delimited("a", "5\n6\n", &options));
+        assert_eq!(2, imported.unwrap());
+        assert_eq!("\n(3)\n(4)\n(5)\n(6)\n", engine.input("a(_)?".into()));
+
+        // input that ends before the commit loses the transaction
+        engine.input("begin a(7)".into());
+        assert!(engine.end_input().is_err());
+        assert!(engine.end_input().is_ok());
+        assert_eq!("\n(3)\n(4)\n(5)\n(6)\n", engine.input("a(_)?".into()));
+    }
+
+    #[test]
+    fn transactions_are_persisted_on_commit() {
+        let directory =
+            std::env::temp_dir().join(format!("datalang_transactions_{}", std::process::id()));
+        std::fs::remove_dir_all(&directory).ok();
+
+        let mut engine = Engine::open(&directory).unwrap();
+        engine.input("a(1) begin a(2) a(3)".into());
+        let log = directory.join("log.0.dl");
+        assert_eq!("a(1)\n", std::fs::read_to_string(&log).unwrap());
+        engine.input("commit".into());
+        assert_eq!(
+            "a(1)\nbegin\na(2)\na(3)\ncommit\n",
+            std::fs::read_to_string(&log).unwrap()
+        );
+
+        // a crash halfway through logging a transaction loses all of it
+        std::fs::write(&log, "a(1)\nbegin\na(2)\na(3)\n").unwrap();
+        let mut reopened = Engine::open(&directory).unwrap();
+        assert_eq!("\n(1)\n", reopened.input("a(_)?".into()));
+        reopened.input("a(4)".into());
+        let mut reopened = Engine::open(&directory).unwrap();
+        assert_eq!("\n(1)\n(4)\n", reopened.input("a(_)?".into()));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn storage_is_replayed_with_the_engine_settings() {
+        let directory =
+            std::env::temp_dir().join(format!("datalang_settings_{}", std::process::id()));
+        std::fs::remove_dir_all(&directory).ok();
+
+        let mut engine = Engine::new();
+        engine.set_recursion_limit(8);
+        engine.load(&directory).unwrap();
+        engine.input("gen(0) gen(a+1) :- gen(a) && a < 7 :- gen(x) && x > 7".into());
+        let gen = "\n(0)\n(1)\n(2)\n(3)\n(4)\n(5)\n(6)\n(7)\n";
+        assert_eq!(gen, engine.input("gen(_)?".into()));
+
+        // with the default limit the constraint can't be checked
+        assert!(Engine::open(&directory).is_err());
+        let mut reopened = Engine::new();
+        reopened.set_recursion_limit(8);
+        reopened.load(&directory).unwrap();
+        assert_eq!(gen, reopened.input("gen(_)?".into()));
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn constraints() {
+        let mut engine = Engine::new();
+        engine.input("employee(1,10) departmentof(1,5)".into());
+        let outcomes = engine.run(":- employee(x,_) && !departmentof(x,_) employee(2,20)".into());
+        match outcomes.last() {
+            Some(Err(err)) => assert_eq!(
+                "the constraint :- (employee(x,_) && !departmentof(x,_)) is violated when x = 2",
+                err.to_string()
+            ),
+            other => panic!("expected a violation, got {other:?}"),
+        }
+        assert_eq!("\n(1, 10)\n", engine.input("employee(x,y)?".into()));
+
+        // the batch is checked once all its lines ran
+        engine.input("employee(2,20) departmentof(2,5)".into());
+        assert_eq!(
+            "\n(1, 10)\n(2, 20)\n",
+            engine.input("employee(x,y)?".into())
+        );
+
+        // a constraint that already doesn't hold is refused
+        let outcomes = engine.run(":- employee(x,y) && y > 15".into());
+        assert!(matches!(
+            outcomes.last(),
+            Some(Err(DlError::Runtime {
+                error: RuntimeError::ConstraintViolated { .. },
+                ..
+            }))
+        ));
+        assert!(!engine.export().contains("y > 15"));
+
+        engine.input(":- age(x,a) && a < 0 age(1,30)".into());
+        let outcomes = engine.run("begin age(2,0-1) commit".into());
+        assert!(outcomes
+            .last()
+            .unwrap()
+            .as_ref()
+            .is_err_and(|err| err.to_string().contains("when a = -1, x = 2")));
+        assert_eq!("\n(1, 30)\n", engine.input("age(x,a)?".into()));
+
+        let mut options = ImportOptions::new(Delimiter::Comma);
+        options.column_types = Some(vec![ColumnType::Number, ColumnType::Number]);
+        assert!(engine
+            .import_delimited("age", "3,40\n4,0-2\n", &options)
+            .is_err());
+        assert!(engine
+            .import_delimited("age", "3,40\n4,-2\n", &options)
+            .is_err());
+        assert_eq!("\n(1, 30)\n", engine.input("age(x,a)?".into()));
+
+        // constraints are exported with the fact base
+        let mut reloaded = Engine::new();
+        reloaded.input(engine.export());
+        assert!(reloaded.run("age(5,0-5)".into()).last().unwrap().is_err());
+
+        // a rule with a literal head is neither a fact nor a constraint
+        let mut engine = Engine::new();
+        engine.input("q(1) s(2) :- q(1)".into());
+        assert!(engine.export().contains("s(2) :- q(1)"));
+        engine.input("!q(1)".into());
+        assert_eq!("\nEmpty Result\n", engine.input("s(_)?".into()));
+
+        // but a constraint on its own line after a fact is one
+        let mut engine = Engine::new();
+        let outcomes = engine.run("employee(1,10)\n:- employee(x,_) && !departmentof(x,_)".into());
+        assert!(matches!(
+            outcomes.last(),
+            Some(Err(DlError::Runtime {
+                error: RuntimeError::ConstraintViolated { .. },
+                ..
+            }))
+        ));
+        engine.input(
+            "departmentof(1,5) employee(1,10)\n:- employee(x,_) && !departmentof(x,_)".into(),
+        );
+        let exported = engine.export();
+        assert_eq!(
+            "departmentof(1,5)\nemployee(1,10)\n:- (employee(x,_) && !departmentof(x,_))\n",
+            exported
+        );
+        let mut reloaded = Engine::new();
+        reloaded.input(exported);
+        assert_eq!("\n(1, 10)\n", reloaded.input("employee(x,y)?".into()));
+
+        // only the constraints reading a changed relation are checked, through rules too, and
+        // undoing a batch drops the relations it created
+        let mut engine = Engine::new();
+        engine.input("edge(1,2) reach(x,y) :- edge(x,y) || (edge(x,z) && reach(z,y))".into());
+        engine.input(":- reach(x,x)".into());
+        assert!(engine
+            .run("node(1) edge(2,1)".into())
+            .last()
+            .unwrap()
+            .is_err());
+        assert_eq!(
+            "edge(1,2)\nreach(x,y) :- (edge(x,y) || (edge(x,z) && reach(z,y)))\n:- reach(x,x)\n",
+            engine.export()
+        );
+    }
+
+    #[test]
+    fn typed_declarations() {
+        let mut engine = Engine::new();
+        engine.input(
+            "decl person(name: string, age: number, tags: array) person(\"bob\", 30, [1])".into(),
+        );
+
+        let refused = [
+            "person(\"ann\", \"30\", [])",
+            "person(\"ann\", 30)",
+            "person(x, y, z) -> person(x, \"old\", z)",
+            "person(x, \"old\", []) :- friend(x)",
+            "decl person(name: string)",
+        ];
+        for line in refused {
+            let outcomes = engine.run(line.into());
+            assert!(
+                matches!(outcomes.last(), Some(Err(DlError::Runtime { .. }))),
+                "{line} wasn't refused"
+            );
+        }
+        assert!(engine.run("person(\"ann\", \"30\", [])".into())[0]
+            .as_ref()
+            .is_err_and(|err| err
+                .to_string()
+                .contains("the column age is a number but \"30\" is a string")));
+
+        // derived truths are checked when they are deduced
+        engine.input("raw(\"ann\", \"young\") person(x, y, []) :- raw(x, y)".into());
+        assert!(engine
+            .run("person(x, y, z)?".into())
+            .last()
+            .unwrap()
+            .is_err());
+
+        // a relation can only be declared when what it holds fits
+        assert!(engine
+            .input("age(\"bob\", \"30\") decl age(who: string, years: number)".into())
+            .contains("the column years is a number"));
+
+        let mut engine = Engine::new();
+        engine.input("decl person(name: string, age: number) person(\"bob\", 30)".into());
+        let exported = engine.export();
+        assert_eq!(
+            "decl person(name: string, age: number)\nperson(\"bob\",30)\n",
+            exported
+        );
+        let mut reloaded = Engine::new();
+        reloaded.input(exported);
+        assert!(reloaded.run("person(\"ann\", \"30\")".into())[0].is_err());
+    }
+
+    #[test]
+    fn named_columns() {
+        let mut engine = Engine::new();
+        engine.input(
+            "decl person(name: string, age: number, tags: array) \
+            person(\"bob\", 30, [1]) person(\"ann\", 4, [])"
+                .into(),
+        );
+        assert_eq!(
+            "\n name   age  tags\n(\"ann\", 4  , []  )\n(\"bob\", 30 , [1] )\n",
+            engine.input("person(age: a, name: n)?".into())
+        );
+
+        engine.input("adult(n) :- person(age: a, name: n) && a > 17".into());
+        assert_eq!("\n(\"bob\")\n", engine.input("adult(n)?".into()));
+        assert_eq!(
+            "\nRemoved 1 truth\n",
+            engine.input("!person(name: \"ann\")".into())
+        );
+
+        for (line, error) in [
+            ("person(agr: a)?", "person has no column agr"),
+            ("person(age: a, age: b)?", "names the column age twice"),
+            ("adult(name: n)?", "adult isn't declared"),
+        ] {
+            assert!(engine.run(line.into())[0]
+                .as_ref()
+                .is_err_and(|err| err.to_string().contains(error)));
+        }
+    }
+
+    #[test]
+    fn key_columns() {
+        let mut engine = Engine::new();
+        engine.input(
+            "decl upsert salary(key emp: number, amount: number) \
+            salary(1,100) salary(2,50) salary(1,200)"
+                .into(),
+        );
+        assert_eq!(
+            "\n emp  amount\n(1  , 200   )\n(2  , 50    )\n",
+            engine.input("salary(e,a)?".into())
+        );
+        assert!(engine
+            .export()
+            .starts_with("decl upsert salary(key emp: number, amount: number)\n"));
+
+        engine.input("decl strict bonus(key emp: number, key year: number, amount: number)".into());
+        engine.input("bonus(1,2020,5) bonus(1,2021,7) bonus(1,2020,5)".into());
+        let outcomes = engine.run("bonus(1,2020,9)".into());
+        assert!(outcomes[0].as_ref().is_err_and(|err| err
+            .to_string()
+            .contains("bonus(1,2020,5) already has the key of bonus(1,2020,9)")));
+
+        // a refused goal leaves the update undone
+        assert!(engine.run("bonus(e,y,a) -> bonus(e,2020,a)".into())[0].is_err());
+        assert_eq!(
+            "\n emp  year  amount\n(1  , 2020, 5     )\n(1  , 2021, 7     )\n",
+            engine.input("bonus(e,y,a)?".into())
+        );
+
+        assert!(engine
+            .input("tenure(1,3) tenure(1,4) decl tenure(key emp: number, years: number)".into())
+            .contains("have the same key"));
+
+        // a key names one truth, `_` would name all of them
+        for line in [
+            "salary(_,8)",
+            "bonus(_,2020,1)",
+            "salary(e,a) -> salary(_,a)",
+        ] {
+            let outcomes = engine.run(line.into());
+            assert!(outcomes[0]
+                .as_ref()
+                .is_err_and(|err| err.to_string().contains("can't be _")));
+        }
+        assert_eq!(
+            "\n emp  amount\n(1  , 200   )\n(2  , 50    )\n",
+            engine.input("salary(e,a)?".into())
+        );
+        assert!(engine
+            .input("tenure2(_,3) decl tenure2(key emp: number, years: number)".into())
+            .contains("can't be _"));
+
+        // derived truths would skip the key, so keyed relations take no rules
+        let outcomes = engine.run("salary(e,a) :- bonus(e,2020,a)".into());
+        assert!(outcomes[0]
+            .as_ref()
+            .is_err_and(|err| err.to_string().contains("can't have rules")));
+        engine.input("perk(e,a) :- bonus(e,2021,a)".into());
+        assert!(engine.run("decl perk(key emp: number, amount: number)".into())[0].is_err());
+        assert_eq!("\n(1, 7)\n", engine.input("perk(e,a)?".into()));
+    }
+
+    #[test]
+    fn truth_indexes() {
+        let mut engine = Engine::new();
+        engine.input("edge(1,2) edge(1,3) edge(2,3) edge(_,4) edge(3,[1])".into());
+        assert_eq!(
+            "\n(1, 2)\n(1, 3)\n(1, 4)\n",
+            engine.input("edge(1,x)?".into())
+        );
+        assert_eq!("\n(1, 3)\n(2, 3)\n", engine.input("edge(x,3)?".into()));
+
+        // the index built by the lookups above has to follow changes
+        engine.input("!edge(1,2) edge(1,5)".into());
+        engine.input("edge(x,3) -> edge(x,x+5)".into());
+        assert_eq!(
+            "\n(1, 4)\n(1, 5)\n(1, 6)\n",
+            engine.input("edge(1,x)?".into())
+        );
+        assert_eq!("\n(2, 7)\n", engine.input("edge(x,7)?".into()));
+        assert_eq!("\n(3, [1])\n", engine.input("edge(3,[1])?".into()));
+    }
+
+    #[test]
+    fn join_plans() {
+        let mut engine = Engine::new();
+        engine.input(
+            "edge(1,2) edge(2,3) edge(3,4) edge(4,5) start(3) \
+            hop(x,z) :- x > 1 && edge(x,y) && edge(y,z) && start(x) \
+            far(x,z) :- edge(x,y) && z = y+10 && !start(z)"
+                .into(),
+        );
+        assert_eq!(
+            "hop(x,z) :- ((((x>1) && edge(x,y)) && edge(y,z)) && start(x))\n \
+            1. start(x)\n \
+            2. (x>1) with x bound\n \
+            3. edge(x,y) with x bound\n \
+            4. edge(y,z) with y bound\n",
+            engine.explain("hop(a,b)?").unwrap()
+        );
+        assert_eq!(
+            "hop(x,z) :- ((((x>1) && edge(x,y)) && edge(y,z)) && start(x))\n \
+            1. (x>1) with x bound\n \
+            2. start(x) with x bound\n \
+            3. edge(x,y) with x bound\n \
+            4. edge(y,z) with y bound\n\
+            far(x,z) :- ((edge(x,y) && (z=y+10)) && !start(z))\n \
+            1. edge(x,y)\n \
+            2. (z=y+10) with y bound\n \
+            3. !start(z) with z bound\n",
+            engine.explain("hop(2,b)? far(a,b)?").unwrap()
+        );
+        assert_eq!("\n(3, 5)\n", engine.input("hop(a,b)?".into()));
+        assert_eq!(
+            "\n(1, 12)\n(2, 13)\n(3, 14)\n(4, 15)\n",
+            engine.input("far(a,b)?".into())
+        );
+        assert!(engine
+            .explain("edge(a,b)? a(1)")
+            .is_err_and(|err| err.to_string().contains("a(1) isn't one")));
+    }
+
+    #[test]
+    fn rule_lookups_use_an_index() {
+        let mut engine = Engine::new();
+        let facts: String = (0..20000).map(|i| format!("{i},{i}\n")).collect();
+        engine
+            .import_delimited("big", &facts, &ImportOptions::new(Delimiter::Comma))
+            .unwrap();
+        let big = RelId {
+            identifier: "big".into(),
+            column_count: 2,
+        };
+
+        // the first lookup builds the index
+        engine.input("r(x,y) :- big(x,y) && y < 0 r(0,y)?".into());
+        let read = engine.get_candidates_read(&big);
+        // reading a rule with a bound argument only goes through the truths with that value
+        for k in 0..50 {
+            engine.input(format!("r({k},y)?"));
+        }
+        assert_eq!(50, engine.get_candidates_read(&big) - read);
+    }
 }
@@ -1,8 +1,11 @@
+use crate::error::Span;
+
 pub fn print_hilighted(
     base_string: &String,
     start: usize,
     end: usize,
     indentation: String,
+    colored: bool,
 ) -> String {
     let context_margin = 30;
 
@@ -57,8 +60,22 @@ pub fn print_hilighted(
         })
         .collect();
 
-    format!(
-        "{}\x1b[1m\x1b[37;41m{}\x1b[0m{}",
-        padded_precontext, padded_error, padded_postcontext
-    )
+    match colored {
+        true => format!(
+            "{}\x1b[1m\x1b[37;41m{}\x1b[0m{}",
+            padded_precontext, padded_error, padded_postcontext
+        ),
+        false => format!(
+            "{}{}{}",
+            padded_precontext, padded_error, padded_postcontext
+        ),
+    }
+}
+
+// the lexer reads the source after a line break, spans are given over the source alone
+pub fn source_span(pos_s: usize, pos_f: usize) -> Span {
+    Span {
+        start: pos_s.saturating_sub(1),
+        end: pos_f.saturating_sub(1),
+    }
 }